@@ -1,385 +1,2297 @@
-use std::io;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
 use std::io::Read;
 use std::path::Path;
 use regex::Regex;
-use csv::Reader;
+use csv::{Reader, ReaderBuilder};
 use std::thread;
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::cell::RefCell;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::Validator;
+use rustyline::{Context, Editor, Helper};
+use std::time::{Duration, Instant};
 use serde::{Serialize, Deserialize};
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, Clone)]
 struct Game{
     id: u8,
     sub_name: String,
     last_save: DateTime<Utc>,
-    oxygen: u8,
+    oxygen: u16,
     player_direction: Direction,
     player_position: (u8, u8, u8),
     real_map: Vec<Vec<Vec<String>>>,
     player_map: Vec<Vec<Vec<String>>>,
+    #[serde(default)]
+    torpedoes: TorpedoBay,
+    #[serde(skip)]
+    torpedo_trail: Vec<(u8, u8)>,
+    #[serde(default)]
+    sonar_cooldowns: SonarCooldowns,
+    #[serde(default = "full_hull")]
+    hull: u8,
+    #[serde(default)]
+    creatures: Vec<Creature>,
+    #[serde(default)]
+    inventory: Vec<Item>,
+    #[serde(default)]
+    currents: Vec<Current>,
+    #[serde(default = "default_layers")]
+    layers: Vec<LayerRules>,
+    #[serde(default)]
+    zones: Vec<Zone>,
+    #[serde(default)]
+    turns_at_depth: u16,
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    outcome: Option<Outcome>,
+    #[serde(default = "default_map_name")]
+    map_name: String,
+    #[serde(default)]
+    seed: Option<u64>,
+    #[serde(default)]
+    stats: Stats,
+    #[serde(default)]
+    mode: GameMode,
+    #[serde(default)]
+    time_limit_ms: Option<u64>,
+    #[serde(default)]
+    elapsed_ms: u64,
+    #[serde(default = "default_mission")]
+    mission: Mission,
+    #[serde(default)]
+    objectives_done: Vec<bool>,
+    #[serde(default)]
+    campaign_level: Option<usize>,
+    #[serde(default)]
+    credits: u32,
+    #[serde(default)]
+    upgrades: Upgrades,
+    #[serde(default)]
+    waypoints: Vec<Waypoint>,
 }
 
-enum Action{
-    Move(Direction),
-    Scan,
-    Shoot,
-    Capture,
-    Save,
-    Help,
-    Quit,
+#[derive(Deserialize, Serialize, Clone)]
+struct Waypoint{
+    label: String,
+    position: (u8, u8, u8),
 }
 
-
-#[derive(Deserialize, Serialize)]
-enum Direction {
-    North,
-    South,
-    East,
-    West,
-    Up,
-    Down,
+#[derive(Deserialize, Serialize, Clone, Default)]
+struct Upgrades{
+    oxygen_tank: u8,
+    torpedo_range: u8,
+    sonar: u8,
+    hull: u8,
 }
 
-fn print_movement(dir: &Direction) -> std::io::Result<()>{
-    match dir {
-        Direction::North => println!("O submarino se move na direção norte"),
-        Direction::South => println!("O submarino se move na direção sul"),
-        Direction::East => println!("O submarino se move na direção leste"),
-        Direction::West => println!("O submarino se move na direção oeste"),
-        Direction::Up => println!("O submarino ascende"),
-        Direction::Down => println!("O submarino desce"),
+impl Upgrades{
+    fn level(&self, upgrade: Upgrade) -> u8 {
+        match upgrade {
+            Upgrade::OxygenTank => self.oxygen_tank,
+            Upgrade::TorpedoRange => self.torpedo_range,
+            Upgrade::Sonar => self.sonar,
+            Upgrade::Hull => self.hull,
+        }
     }
 
-    Ok(())
+    fn level_mut(&mut self, upgrade: Upgrade) -> &mut u8 {
+        match upgrade {
+            Upgrade::OxygenTank => &mut self.oxygen_tank,
+            Upgrade::TorpedoRange => &mut self.torpedo_range,
+            Upgrade::Sonar => &mut self.sonar,
+            Upgrade::Hull => &mut self.hull,
+        }
+    }
 }
 
-#[allow(unused_variables, unused_assignments)]
-fn create_save_file() -> std::io::Result<()> {
-    let title_pattern = Regex::new(r"^[A-Za-z]{1,12}$").unwrap();
-    let mut sub_name = String::new();
-    let mut player_map =  vec![vec![vec!["".to_string(); 50]; 50];3];
-    player_map[1][41][10] = "player".to_string();
+#[derive(Clone, Copy, PartialEq)]
+enum Upgrade{
+    OxygenTank,
+    TorpedoRange,
+    Sonar,
+    Hull,
+}
 
-    loop {
-        println!("Insira o nome de seu submarino (max 12 chars):");
-        sub_name = get_player_input();
+impl Upgrade{
+    fn all() -> [Upgrade; 4] {
+        [Upgrade::OxygenTank, Upgrade::TorpedoRange, Upgrade::Sonar, Upgrade::Hull]
+    }
 
-        if title_pattern.is_match(&sub_name){
-            break
-        }else{
-            println!("Título inválido")
+    fn name(&self) -> &'static str {
+        match self {
+            Upgrade::OxygenTank => "Tanque de oxigênio ampliado (+40 de oxigênio)",
+            Upgrade::TorpedoRange => "Propulsores de torpedo (+3 casas de alcance)",
+            Upgrade::Sonar => "Sonar aprimorado (+2 casas de alcance lateral, +1 vertical)",
+            Upgrade::Hull => "Casco reforçado (-25% de integridade exigida nos níveis profundos)",
         }
     }
 
-    let cur_game = Game{
-        id: get_save_files().expect("Erro ao carregar arquivos salvos.").len() as u8 + 1,
-        sub_name: String::from(sub_name),
-        last_save: Utc::now(),
-        oxygen: 240,
-        player_direction: Direction::North,
-        player_position: (11, 41, 1),
-        real_map: load_map_csv().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", e)))?,
-        player_map: player_map,
-    };
+    fn base_price(&self) -> u32 {
+        match self {
+            Upgrade::OxygenTank => 100,
+            Upgrade::TorpedoRange => 120,
+            Upgrade::Sonar => 150,
+            Upgrade::Hull => 200,
+        }
+    }
+}
 
-    let save_name = format!("{}-{}.json", cur_game.id, cur_game.sub_name);
+fn oxygen_capacity(game: &Game) -> u16 {
+    game.rules.starting_oxygen + game.upgrades.oxygen_tank as u16 * 40
+}
 
-    let filepath = format!("saves/{}", save_name);
+fn scan_window(game: &Game) -> (u8, u8) {
+    (game.rules.scan_width + game.upgrades.sonar * 2, game.rules.scan_height + game.upgrades.sonar)
+}
 
-    let mut file = File::create(filepath)?;
-    let data = serde_json::to_string(&cur_game)?;
-    file.write_all(data.as_bytes())?;
+fn torpedo_range(game: &Game) -> u8 {
+    game.rules.torpedo_range + game.upgrades.torpedo_range * 3
+}
 
-    println!("Jogo salvo como: {}", save_name);
+fn hull_required(game: &Game, layer: u8) -> u8 {
+    layer_rules(game, layer).hull_required.saturating_sub(game.upgrades.hull * 25)
+}
 
-    println!("\nA muitas décadas atrás, o Barão Matthew Sheldrake afundou junto de seu návio nessas águas");
-    println!("Reza a lenda, que ele levava consigo toda sua fortuna dentro de um cofre dourado...");
-    println!("Com o seu submarino '{}', cabe a você encontrar este tesouro!", &cur_game.sub_name);
+#[derive(Deserialize, Clone)]
+struct CampaignLevel{
+    name: String,
+    map: String,
+    mission: String,
+    oxygen: u16,
+}
 
-    game_loop(cur_game, false);
-    Ok(())
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+enum GameMode{
+    #[default]
+    Standard,
+    Daily,
+    TimeAttack,
+    Campaign,
 }
 
-#[allow(unused_variables)]
-fn update_save_file(game: &Game) -> std::io::Result<()>{
-    let save_files = get_save_files()?;
+impl GameMode{
+    fn name(&self) -> &'static str {
+        match self {
+            GameMode::Standard => "Clássico",
+            GameMode::Daily => "Desafio diário",
+            GameMode::TimeAttack => "Contra o relógio",
+            GameMode::Campaign => "Campanha",
+        }
+    }
+}
 
-    let save_name = format!("{}-{}.json", game.id, game.sub_name);
-    let filepath = format!("saves/{}", save_name);
+struct SeededRng(u64);
 
-    let mut file = File::create(filepath)?;
-    let data = serde_json::to_string(&game)?;
-    file.write_all(data.as_bytes())?;
+impl SeededRng{
+    fn new(seed: u64) -> Self {
+        SeededRng(seed ^ 0x9E37_79B9_7F4A_7C15)
+    }
 
-    println!("Jogo salvo com sucesso!");
-    Ok(())
-}
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
 
-fn get_save_files() -> std::io::Result<Vec<String>> {
-    let entries = fs::read_dir("saves")?;
+    fn below(&mut self, max: usize) -> usize {
+        (self.next() % max as u64) as usize
+    }
+}
 
-    let reg = Regex::new(r"^\d+-").unwrap();
+fn default_map_name() -> String {
+    "base_map".to_string()
+}
 
-    let mut match_files = Vec::new();
+#[derive(Deserialize, Serialize, Default, Clone)]
+struct Stats{
+    turns: u32,
+    rocks_destroyed: u32,
+    #[serde(default)]
+    torpedoes_fired: u32,
+    #[serde(default)]
+    layers_visited: Vec<u8>,
+    #[serde(default)]
+    moves: MoveCounts,
+    #[serde(default)]
+    collisions: u32,
+    #[serde(default)]
+    scans: u32,
+    #[serde(default)]
+    turns_per_layer: Vec<u32>,
+    #[serde(default)]
+    distance: u32,
+}
 
-    for en in entries {
-        let en = en?;
-        let path = en.path();
+#[derive(Deserialize, Serialize, Default, Clone)]
+struct MoveCounts{
+    north: u32,
+    south: u32,
+    east: u32,
+    west: u32,
+    up: u32,
+    down: u32,
+}
 
-        if path.is_file(){
-            if let Some(filename) = path.file_name(){
-                if let Some(filename_str) = filename.to_str(){
-                    if reg.is_match(filename_str){
-                        match_files.push(filename_str.to_string());
-                    }
-                }
-            }
+impl MoveCounts{
+    fn count(&mut self, dir: Direction) -> &mut u32 {
+        match dir {
+            Direction::North => &mut self.north,
+            Direction::South => &mut self.south,
+            Direction::East => &mut self.east,
+            Direction::West => &mut self.west,
+            Direction::Up => &mut self.up,
+            Direction::Down => &mut self.down,
         }
     }
+}
 
-    Ok(match_files)
-    
+#[derive(Deserialize, Serialize, Default)]
+struct Profile{
+    achievements: Vec<UnlockedAchievement>,
+    rocks_destroyed: u32,
+    #[serde(default)]
+    last_daily: Option<NaiveDate>,
 }
 
-#[allow(unused_variables)]
-fn load_save_menu() ->std::io::Result<Game>{
-    let save_files = get_save_files()?;
-    let input = String::new();
+#[derive(Deserialize, Serialize)]
+struct UnlockedAchievement{
+    achievement: Achievement,
+    date: DateTime<Utc>,
+}
 
-    for sf in &save_files {
-        println!("{}", sf);
-    }
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+enum Achievement{
+    FirstWin,
+    Pacifist,
+    DeepBreath,
+    Explorer,
+    Demolisher,
+}
 
-    loop{
-        println!("\nEntre o nome do jogo que deseja carregar:");
+impl Achievement{
+    fn all() -> [Achievement; 5] {
+        [Achievement::FirstWin, Achievement::Pacifist, Achievement::DeepBreath, Achievement::Explorer, Achievement::Demolisher]
+    }
 
-        let input = get_player_input();
-        for sf in &save_files{
-            if sf.contains(&input) {
-                let game = load_save_file(&sf)?;
-                return Ok(game);
-            }
+    fn name(&self) -> &'static str {
+        match self {
+            Achievement::FirstWin => "Caçador de tesouros",
+            Achievement::Pacifist => "Pacifista",
+            Achievement::DeepBreath => "Fôlego de sobra",
+            Achievement::Explorer => "Explorador",
+            Achievement::Demolisher => "Demolidor",
         }
-        println!("Entrada inválida");
     }
 
+    fn description(&self) -> &'static str {
+        match self {
+            Achievement::FirstWin => "Vença uma partida",
+            Achievement::Pacifist => "Vença uma partida sem disparar nenhum torpedo",
+            Achievement::DeepBreath => "Vença uma partida com mais da metade do oxigênio inicial",
+            Achievement::Explorer => "Visite todos os níveis do oceano em uma partida",
+            Achievement::Demolisher => "Destrua 50 rochas, somando todas as partidas",
+        }
+    }
 }
 
-#[allow(unused_assignments)]
-fn delete_save_menu() -> Result<(), Box<dyn std::error::Error>>{
-    let save_files = get_save_files()?;
-    let mut input = String::new();
-    let mut confirmation_input = String::new();
+#[derive(Deserialize, Serialize)]
+struct LeaderboardEntry{
+    #[serde(default)]
+    mode: GameMode,
+    sub_name: String,
+    map_name: String,
+    seed: Option<u64>,
+    difficulty: String,
+    score: u32,
+    turns: u32,
+    date: DateTime<Utc>,
+}
 
-    for sf in &save_files {
-        println!("{}", sf);
-    }
+#[derive(Deserialize, Serialize, Clone)]
+struct Current{
+    position: (u8, u8, u8),
+    direction: Direction,
+    strength: u8,
+    revealed: bool,
+}
 
-    loop{
-        println!("\nEntre o nome do jogo que deseja deletar:");
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+enum Item{
+    OxygenTank,
+    TorpedoCrate,
+    RepairKit,
+    Pearl,
+    GoldCoins,
+    Artifact,
+    Vault,
+}
 
-        input = get_player_input();
-        for sf in &save_files{
-            if sf.contains(&input) {
-                loop{
-                    println!("Jogo {} encontrado, desejar excluir o jogo salvo? Essa ação não pode ser desfeita. (s/n)", &sf);
-                    confirmation_input = get_player_input();
-                    match confirmation_input.as_str(){
-                        "s" => {
-                            delete_save_file(sf);
-                            return Ok(());
-                        },
-                        "n" => return Ok(()),
-                        _ => println!("Confirmação inválida"),
-                    }
+impl Item{
+    fn from_tile(tile: &str) -> Option<Item> {
+        match tile {
+            "oxygenTank" => Some(Item::OxygenTank),
+            "torpedoCrate" => Some(Item::TorpedoCrate),
+            "repairKit" => Some(Item::RepairKit),
+            "pearl" => Some(Item::Pearl),
+            "goldCoins" => Some(Item::GoldCoins),
+            "artifact" => Some(Item::Artifact),
+            "treasure" => Some(Item::Vault),
+            _ => None,
+        }
+    }
 
-                }
-            }
+    fn name(&self) -> &'static str {
+        match self {
+            Item::OxygenTank => "Tanque de oxigênio",
+            Item::TorpedoCrate => "Caixa de torpedos",
+            Item::RepairKit => "Kit de reparos",
+            Item::Pearl => "Pérola negra",
+            Item::GoldCoins => "Saco de moedas de ouro",
+            Item::Artifact => "Artefato antigo",
+            Item::Vault => "Cofre de Matthew Sheldrake",
         }
-        println!("Entrada inválida");
     }
 
+    fn value(&self) -> u32 {
+        match self {
+            Item::Pearl => 100,
+            Item::GoldCoins => 250,
+            Item::Artifact => 150,
+            Item::Vault => 1000,
+            _ => 0,
+        }
+    }
 }
 
-fn load_save_file(save_name: &str) -> std::io::Result<Game> {
+#[derive(Deserialize, Serialize, Clone)]
+struct Mission{
+    name: String,
+    briefing: Vec<String>,
+    ending: Vec<String>,
+    objectives: Vec<Objective>,
+}
 
-    let filepath = format!("saves/{}", save_name);
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(tag = "type")]
+enum Objective{
+    Treasure{ value: u32 },
+    Reach{ x: u8, y: u8, z: u8 },
+    Scan{ layer: u8, percent: u8 },
+    Collect{ item: Item, count: u8 },
+    Destroy{ cells: Vec<(u8, u8, u8)> },
+    Surface{ item: Item },
+}
 
-    let mut file = File::open(&filepath)?;
+fn default_mission() -> Mission {
+    load_mission("assets/missions/01-sheldrake.json").unwrap_or_else(|_| Mission{
+        name: "O cofre de Sheldrake".to_string(),
+        briefing: Vec::new(),
+        ending: vec!["Sucesso! Você obteve o 'Cofre de Matthew Sheldrake'!".to_string()],
+        objectives: vec![Objective::Treasure{ value: 1000 }],
+    })
+}
 
-    let mut data = String::new();
-    file.read_to_string(&mut data)?;
+#[derive(Deserialize, Serialize, Clone)]
+struct Creature{
+    kind: CreatureKind,
+    position: (u8, u8, u8),
+    heading: Direction,
+    health: u8,
+    fleeing: u8,
+    spotted: bool,
+}
 
-    let loaded_game: Game = serde_json::from_str(&data)?;
-    
-    println!("Jogo {} carregado com sucesso!", save_name);
-    
-    Ok(loaded_game)
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+enum CreatureKind{
+    Eel,
+    Squid,
 }
 
-fn delete_save_file(save_name: &str){
-    let filepath = format!("saves/{}", save_name);
+impl CreatureKind{
+    fn name(&self) -> &'static str {
+        match self {
+            CreatureKind::Eel => "enguia elétrica",
+            CreatureKind::Squid => "lula gigante",
+        }
+    }
+}
 
-    let _ = fs::remove_file(&filepath);
-    
-    println!("Jogo {} deletado com sucesso", save_name);
+fn full_hull() -> u8 {
+    100
 }
 
-#[allow(unused_assignments)]
-fn load_map_csv() -> Result<Vec<Vec<Vec<String>>>, Box<dyn std::error::Error>> {
-    let mut base_map = Reader::from_path("assets/base_map.csv")?;
-    let mut local_map: Vec<Vec<Vec<String>>> = vec![vec![vec!["".to_string(); 50]; 50];3];
+#[derive(Deserialize, Serialize, Clone)]
+struct Rules{
+    name: String,
+    starting_oxygen: u16,
+    torpedo_range: u8,
+    scan_width: u8,
+    scan_height: u8,
+    collision_damage: u8,
+    #[serde(default)]
+    ironman: bool,
+    #[serde(default = "default_score_multiplier")]
+    score_multiplier: u16,
+    #[serde(default = "default_undo")]
+    undo: bool,
+}
 
-    let mut h_index = 0;
+fn default_score_multiplier() -> u16 {
+    100
+}
 
-    for (x_index, x_value) in base_map.records().enumerate() {
-        let row = x_value?;
-        h_index = x_index/50;
+fn default_undo() -> bool {
+    true
+}
 
-        let items: Vec<String> = row.get(0).unwrap_or("").split(';')
-            .map(|s| s.to_string())
-            .collect();
+impl Rules{
+    fn allows_undo(&self) -> bool {
+        self.undo && !self.ironman
+    }
+}
 
-        for (y_index, y_value) in items.iter().enumerate() {
-            local_map[h_index][x_index%50][y_index] = y_value.to_string();
+impl Default for Rules{
+    fn default() -> Self {
+        Rules{
+            name: "Normal".to_string(),
+            starting_oxygen: 240,
+            torpedo_range: 10,
+            scan_width: 9,
+            scan_height: 6,
+            collision_damage: 10,
+            ironman: false,
+            score_multiplier: default_score_multiplier(),
+            undo: default_undo(),
         }
     }
-    Ok(local_map)
 }
 
-fn load_demo_csv() -> Result<Vec<String>, Box<dyn std::error::Error>>{
-    let mut demo_csv = Reader::from_path("assets/demo.csv")?;
-    let csv_rec = demo_csv.records().next().ok_or("Erro ao carregar demo.csv")??;
-    let demo_inputs: Vec<String> = csv_rec.get(0).unwrap_or("").split(';').map(|s| s.to_string()).collect();
+#[derive(Deserialize)]
+struct SaveSummary{
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    outcome: Option<Outcome>,
+    #[serde(default)]
+    campaign_level: Option<usize>,
+}
 
-    Ok(demo_inputs)
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+enum Outcome{
+    Won,
+    Lost,
 }
 
-fn match_player_input(input: &str) -> Option<Action> {
-    match input.trim().to_lowercase().as_str() {
-        "move north" => Some(Action::Move(Direction::North)),
-        "move south" => Some(Action::Move(Direction::South)),
-        "move east" => Some(Action::Move(Direction::East)),
-        "move west" => Some(Action::Move(Direction::West)),
-        "move up" => Some(Action::Move(Direction::Up)),
-        "move down" => Some(Action::Move(Direction::Down)),
-        "scan" => Some(Action::Scan),
-        "shoot" => Some(Action::Shoot),
-        "capture" => Some(Action::Capture),
-        "save" => Some(Action::Save),
-        "help" => Some(Action::Help),
-        "quit" => Some(Action::Quit),
-        _ => None,
+impl Outcome{
+    fn name(&self) -> &'static str {
+        match self {
+            Outcome::Won => "Vitória",
+            Outcome::Lost => "Derrota",
+        }
     }
 }
 
-fn get_player_input() -> String {
-    let mut input = String::new();
-    io::stdin().read_line(&mut input).expect("Falha ao ler entrada");
-    input.trim().to_string()
+#[derive(Deserialize, Serialize, Clone)]
+struct LayerRules{
+    name: String,
+    oxygen_drain: u8,
+    sonar_range: u8,
+    hull_required: u8,
+    stress_turns: u16,
+    stress_damage: u8,
 }
 
-fn title_screen(){
-    loop{
-        println!("\n1. Novo jogo \n2. Carregar jogo salvo\n3. Demo Game\n4. Deletar um jogo salvo\n5. Sair");
-        let input = get_player_input();
-        match input.as_str() {
-            "1" =>{
-                let _ = create_save_file();
-                break
-            },
-            "2" => {
-                match load_save_menu(){
-                    Ok(game) =>{
-                        game_loop(game, false);
-                    },
-                    Err(e) =>{
-                        println!("Erro ao carregar o jogo: {}", e);
-                    },
-                }
-                break
-            },
-            "3" =>{
-                let mut player_map =  vec![vec![vec!["".to_string(); 50]; 50];3];
-                player_map[1][41][10] = "player".to_string();
-
-                let demo_game = Game{
-                    id: get_save_files().expect("Erro ao carregar arquivos salvos.").len() as u8 + 1,
-                    sub_name: "Demo".to_string(),
-                    last_save: Utc::now(),
-                    oxygen: 240,
-                    player_direction: Direction::North,
-                    player_position: (11, 41, 1),
-                    real_map: load_map_csv().map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, format!("{:?}", e))).expect("Falha ao carregar mapa"),
-                    player_map: player_map,
-                };
-
-                game_loop(demo_game, true);
-            },
-            "4" =>{
-                let _ = delete_save_menu();
-            },
-            "5" =>break,
-            _ =>println!("Opção inválida"),
+impl Default for LayerRules{
+    fn default() -> Self {
+        LayerRules{
+            name: "Desconhecido".to_string(),
+            oxygen_drain: 1,
+            sonar_range: 100,
+            hull_required: 0,
+            stress_turns: 0,
+            stress_damage: 0,
         }
     }
+}
 
+fn default_layers() -> Vec<LayerRules> {
+    load_layer_rules("assets/layers.csv").unwrap_or_default()
 }
 
-fn game_hud(game: &Game){
-    let (player_x, player_y, player_z) = game.player_position;
-    println!("\nProfundidade:{}", match player_z{
-        0 => "Águas razas",
-        1 => "Águas profundas",
-        2 => "Abismal",
-        _ => "Error",
-        }
-    );
-    println!("/-------------------\\") ;
-    for y in -6i8..7{
-        print!("|");
-        for x in -9i8..10{
-            let cur_tile_x = player_x as i8 + x;
-            let cur_tile_y = player_y as i8 + y;
-            if cur_tile_x < 0 || cur_tile_y < 0 || cur_tile_x > 49 || cur_tile_y > 49 {
-                print!("#");
-            }else{
-                match game.player_map[player_z as usize][cur_tile_y as usize][cur_tile_x as usize].as_str(){
-                    "borderRock" | "rock" => print!("#"),
-                    "n/a" => print!(" "),
-                    "treasure" => print!("*"),
-                    "player" => {
-                        let direction = &game.player_direction;
-                        match direction{
-                            Direction::North => print!("^"),
-                            Direction::South => print!("v"),
-                            Direction::East => print!(">"),
-                            Direction::West => print!("<"),
-                            _ => print!("^"),
-                        }
-                    },
-                    _ => print!(" "),
-                }
-            }
+fn layer_rules(game: &Game, layer: u8) -> LayerRules {
+    game.layers.get(layer as usize).cloned().unwrap_or_default()
+}
+
+fn sonar_reach(game: &Game, reach: u8) -> u8 {
+    let rules = layer_rules(game, game.player_position.2);
+    (reach as u16 * rules.sonar_range as u16 / 100) as u8
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+struct Zone{
+    position: (u8, u8, u8),
+    kind: ZoneKind,
+    revealed: bool,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+enum ZoneKind{
+    Vent,
+    Dark,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone)]
+struct SonarCooldowns{
+    short: u8,
+    long: u8,
+    vertical: u8,
+}
+
+impl SonarCooldowns{
+    fn turns(&mut self, mode: SonarMode) -> &mut u8 {
+        match mode {
+            SonarMode::Short => &mut self.short,
+            SonarMode::Long => &mut self.long,
+            SonarMode::Vertical => &mut self.vertical,
         }
-        println!("|");
     }
-    println!("\\-------------------/") ;
 
-    println!("Oxigênio:{}", game.oxygen);
+    fn tick(&mut self){
+        self.short = self.short.saturating_sub(1);
+        self.long = self.long.saturating_sub(1);
+        self.vertical = self.vertical.saturating_sub(1);
+    }
 }
 
-fn move_sub(game: &mut Game,dir: Direction){
-    let (cur_x, cur_y, cur_z) = game.player_position;
-    let (mut next_x, mut next_y, mut next_z) = (cur_x, cur_y, cur_z);
+#[derive(Clone, Copy, PartialEq)]
+enum SonarMode{
+    Short,
+    Long,
+    Vertical,
+}
 
-    let _ = print_movement(&dir);
+impl SonarMode{
+    fn name(&self) -> &'static str {
+        match self {
+            SonarMode::Short => "curto alcance",
+            SonarMode::Long => "longo alcance",
+            SonarMode::Vertical => "vertical",
+        }
+    }
 
-    match dir {
+    fn oxygen_cost(&self) -> u8 {
+        match self {
+            SonarMode::Short => 0,
+            SonarMode::Long => 3,
+            SonarMode::Vertical => 2,
+        }
+    }
+
+    fn cooldown(&self) -> u8 {
+        match self {
+            SonarMode::Short => 0,
+            SonarMode::Long => 4,
+            SonarMode::Vertical => 3,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+struct TorpedoBay{
+    standard: u8,
+    wide: u8,
+    depth: u8,
+    loaded: Torpedo,
+}
+
+impl Default for TorpedoBay{
+    fn default() -> Self {
+        TorpedoBay{
+            standard: 10,
+            wide: 2,
+            depth: 2,
+            loaded: Torpedo::Standard,
+        }
+    }
+}
+
+impl TorpedoBay{
+    fn ammo(&mut self, kind: Torpedo) -> &mut u8 {
+        match kind {
+            Torpedo::Standard => &mut self.standard,
+            Torpedo::Wide => &mut self.wide,
+            Torpedo::Depth => &mut self.depth,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+enum Torpedo{
+    Standard,
+    Wide,
+    Depth,
+}
+
+impl Torpedo{
+    fn name(&self) -> &'static str {
+        match self {
+            Torpedo::Standard => "torpedo padrão",
+            Torpedo::Wide => "torpedo de área",
+            Torpedo::Depth => "carga de profundidade",
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Action{
+    Move(Direction),
+    Scan(SonarMode),
+    Shoot(Option<Torpedo>),
+    Load(Torpedo),
+    Capture,
+    Inventory,
+    Use(Item),
+    Repair,
+    Save,
+    Leaderboard,
+    Stats,
+    Shop,
+    Buy(Upgrade),
+    Undo,
+    Redo,
+    Map(MapView, bool),
+    Mark(Option<(u8, u8, u8)>, String),
+    Unmark(String),
+    Waypoints,
+    Go(String),
+    Where,
+    Help,
+    Quit,
+}
+
+#[derive(Clone, Copy)]
+enum MapView{
+    Current,
+    Layer(u8),
+    All,
+}
+
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq)]
+enum Direction {
+    North,
+    South,
+    East,
+    West,
+    Up,
+    Down,
+}
+
+impl Direction{
+    fn name(&self) -> &'static str {
+        match self {
+            Direction::North => "Norte",
+            Direction::South => "Sul",
+            Direction::East => "Leste",
+            Direction::West => "Oeste",
+            Direction::Up => "Acima",
+            Direction::Down => "Abaixo",
+        }
+    }
+}
+
+fn print_movement(dir: &Direction) -> std::io::Result<()>{
+    match dir {
+        Direction::North => println!("O submarino se move na direção norte"),
+        Direction::South => println!("O submarino se move na direção sul"),
+        Direction::East => println!("O submarino se move na direção leste"),
+        Direction::West => println!("O submarino se move na direção oeste"),
+        Direction::Up => println!("O submarino ascende"),
+        Direction::Down => println!("O submarino desce"),
+    }
+
+    Ok(())
+}
+
+fn find_start(real_map: &[Vec<Vec<String>>]) -> (u8, u8, u8) {
+    for (z, layer) in real_map.iter().enumerate(){
+        for (y, row) in layer.iter().enumerate(){
+            if let Some(x) = row.iter().position(|tile| tile == "player") {
+                return (x as u8, y as u8, z as u8);
+            }
+        }
+    }
+    (11, 41, 1)
+}
+
+fn new_game(sub_name: String, rules: Rules, map_path: &str) -> std::io::Result<Game> {
+    let mut real_map = load_map_csv(map_path).map_err(|e| std::io::Error::other(format!("{:?}", e)))?;
+    let creatures = spawn_creatures(&mut real_map);
+    let currents = load_currents_csv(&map_path.replace(".csv", "_currents.csv")).map_err(|e| std::io::Error::other(format!("{:?}", e)))?;
+    let (start_x, start_y, start_z) = find_start(&real_map);
+    let mut player_map =  vec![vec![vec!["".to_string(); 50]; 50];3];
+    player_map[start_z as usize][start_y as usize][start_x as usize] = "player".to_string();
+
+    Ok(Game{
+        id: get_save_files()?.len() as u8 + 1,
+        sub_name,
+        last_save: Utc::now(),
+        oxygen: rules.starting_oxygen,
+        player_direction: Direction::North,
+        player_position: (start_x, start_y, start_z),
+        real_map,
+        player_map,
+        torpedoes: TorpedoBay::default(),
+        torpedo_trail: Vec::new(),
+        sonar_cooldowns: SonarCooldowns::default(),
+        hull: full_hull(),
+        creatures,
+        inventory: Vec::new(),
+        currents,
+        layers: load_layer_rules("assets/layers.csv").map_err(|e| std::io::Error::other(format!("{:?}", e)))?,
+        zones: load_zones_csv(&map_path.replace(".csv", "_zones.csv")).map_err(|e| std::io::Error::other(format!("{:?}", e)))?,
+        turns_at_depth: 0,
+        rules,
+        outcome: None,
+        map_name: Path::new(map_path).file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_else(default_map_name),
+        seed: None,
+        stats: Stats{
+            layers_visited: vec![start_z],
+            ..Stats::default()
+        },
+        mode: GameMode::Standard,
+        time_limit_ms: None,
+        elapsed_ms: 0,
+        mission: default_mission(),
+        objectives_done: Vec::new(),
+        campaign_level: None,
+        credits: 0,
+        upgrades: Upgrades::default(),
+        waypoints: Vec::new(),
+    })
+}
+
+fn load_campaign(path: &str) -> Result<Vec<CampaignLevel>, Box<dyn std::error::Error>> {
+    let mut campaign_csv = ReaderBuilder::new().delimiter(b';').from_path(path)?;
+    let mut levels = Vec::new();
+    for record in campaign_csv.deserialize() {
+        levels.push(record?);
+    }
+    Ok(levels)
+}
+
+fn campaign_game(sub_name: String, level: &CampaignLevel, index: usize) -> std::io::Result<Game> {
+    let rules = Rules{
+        starting_oxygen: level.oxygen,
+        ..Rules::default()
+    };
+    let mut game = new_game(sub_name, rules, &level.map)?;
+    game.mission = load_mission(&level.mission).map_err(|e| std::io::Error::other(format!("{:?}", e)))?;
+    game.mode = GameMode::Campaign;
+    game.campaign_level = Some(index);
+    println!("\nCampanha - Nível {}: {}", index + 1, level.name);
+    Ok(game)
+}
+
+fn advance_campaign(game: &Game) -> Option<Game> {
+    let index = game.campaign_level? + 1;
+    let campaign = match load_campaign("assets/campaign.csv") {
+        Ok(campaign) => campaign,
+        Err(e) => {
+            println!("Erro ao carregar a campanha: {}", e);
+            return None;
+        },
+    };
+    let Some(level) = campaign.get(index) else {
+        println!("\nParabéns! Você concluiu todos os níveis da campanha!");
+        return None;
+    };
+
+    println!("\nNível concluído! O próximo nível foi desbloqueado...");
+    let mut next = match campaign_game(game.sub_name.clone(), level, index) {
+        Ok(next) => next,
+        Err(e) => {
+            println!("Erro ao carregar o próximo nível: {}", e);
+            return None;
+        },
+    };
+    next.id = game.id;
+    next.torpedoes = game.torpedoes.clone();
+    next.credits = game.credits;
+    next.upgrades = game.upgrades.clone();
+    next.oxygen = oxygen_capacity(&next);
+    next.inventory = game.inventory.iter().copied().filter(|item| item.value() == 0).collect();
+    if let Err(e) = update_save_file(&next) {
+        println!("Falha ao salvar o progresso da campanha: {}", e);
+    }
+
+    println!("\nMissão: {}", next.mission.name);
+    for line in &next.mission.briefing{
+        println!("{}", line.replace("{sub_name}", &next.sub_name));
+    }
+    Some(next)
+}
+
+fn daily_seed(date: NaiveDate) -> u64 {
+    date.format("%Y%m%d").to_string().parse().unwrap_or(0)
+}
+
+fn open_water_cells(game: &Game, layer: usize) -> Vec<(u8, u8)> {
+    let mut cells = Vec::new();
+    for (y, row) in game.real_map[layer].iter().enumerate(){
+        for (x, tile) in row.iter().enumerate(){
+            if tile == "n/a" && creature_at(game, (x as u8, y as u8, layer as u8)).is_none() {
+                cells.push((x as u8, y as u8));
+            }
+        }
+    }
+    cells
+}
+
+fn apply_seed(game: &mut Game, seed: u64){
+    let mut rng = SeededRng::new(seed);
+    game.seed = Some(seed);
+
+    for layer in 0..game.real_map.len(){
+        let mut items = Vec::new();
+        for (y, row) in game.real_map[layer].iter_mut().enumerate(){
+            for (x, tile) in row.iter_mut().enumerate(){
+                if matches!(Item::from_tile(tile), Some(item) if item != Item::Vault) {
+                    items.push(((x as u8, y as u8), std::mem::replace(tile, "n/a".to_string())));
+                }
+            }
+        }
+        for (origin, item) in items{
+            let cells = open_water_cells(game, layer);
+            let (x, y) = if cells.is_empty() { origin } else { cells[rng.below(cells.len())] };
+            game.real_map[layer][y as usize][x as usize] = item;
+        }
+    }
+
+    let (start_x, start_y, _) = game.player_position;
+    for index in 0..game.creatures.len(){
+        let layer = game.creatures[index].position.2;
+        let cells: Vec<(u8, u8)> = open_water_cells(game, layer as usize).into_iter()
+            .filter(|&(x, y)| x.abs_diff(start_x) > 10 || y.abs_diff(start_y) > 10)
+            .collect();
+        if !cells.is_empty() {
+            let (x, y) = cells[rng.below(cells.len())];
+            game.creatures[index].position = (x, y, layer);
+        }
+    }
+}
+
+fn start_daily_challenge(profile: &mut Profile) -> bool {
+    let today = Utc::now().date_naive();
+    if profile.last_daily == Some(today) {
+        println!("Você já tentou o desafio diário de hoje! Volte amanhã para um novo desafio.");
+        return false;
+    }
+    profile.last_daily = Some(today);
+    if let Err(e) = save_profile(profile) {
+        println!("Falha ao salvar o perfil: {}", e);
+    }
+    true
+}
+
+#[allow(unused_variables, unused_assignments)]
+fn create_save_file(mode: GameMode) -> std::io::Result<()> {
+    let title_pattern = Regex::new(r"^[A-Za-z]{1,12}$").unwrap();
+    let mut sub_name = String::new();
+
+    loop {
+        println!("Insira o nome de seu submarino (max 12 chars):");
+        sub_name = get_player_input();
+
+        if title_pattern.is_match(&sub_name){
+            break
+        }else{
+            println!("Título inválido")
+        }
+    }
+
+    let mut cur_game = match mode {
+        GameMode::Standard => {
+            let rules = choose_rules_menu();
+            let mission = choose_mission_menu();
+            let mut game = new_game(sub_name, rules, "assets/base_map.csv")?;
+            game.mission = mission;
+            game
+        },
+        GameMode::Campaign => {
+            let campaign = load_campaign("assets/campaign.csv").map_err(|e| std::io::Error::other(format!("{:?}", e)))?;
+            let first_level = campaign.first().ok_or(std::io::Error::other("campanha vazia"))?;
+            campaign_game(sub_name, first_level, 0)?
+        },
+        _ => new_game(sub_name, Rules::default(), "assets/base_map.csv")?,
+    };
+    cur_game.mode = mode;
+    match mode {
+        GameMode::Daily => {
+            let today = Utc::now().date_naive();
+            apply_seed(&mut cur_game, daily_seed(today));
+            println!("Desafio diário de {}! Os itens e criaturas foram espalhados pelo oceano...", today.format("%d/%m/%Y"));
+        },
+        GameMode::TimeAttack => {
+            cur_game.time_limit_ms = Some(10 * 60 * 1000);
+            println!("Contra o relógio! Você tem 10 minutos para encontrar o tesouro...");
+        },
+        GameMode::Standard | GameMode::Campaign => {},
+    }
+
+    let save_name = format!("{}-{}.json", cur_game.id, cur_game.sub_name);
+
+    let filepath = format!("saves/{}", save_name);
+
+    let mut file = File::create(filepath)?;
+    let data = serde_json::to_string(&cur_game)?;
+    file.write_all(data.as_bytes())?;
+
+    println!("Jogo salvo como: {}", save_name);
+
+    println!("\nMissão: {}", cur_game.mission.name);
+    for line in &cur_game.mission.briefing{
+        println!("{}", line.replace("{sub_name}", &cur_game.sub_name));
+    }
+
+    game_loop(cur_game, false);
+    Ok(())
+}
+
+fn load_mission(path: &str) -> Result<Mission, Box<dyn std::error::Error>> {
+    let data = fs::read_to_string(path)?;
+    let mission: Mission = serde_json::from_str(&data)?;
+    let in_bounds = |&(x, y, z): &(u8, u8, u8)| x < 50 && y < 50 && z < 3;
+    for objective in &mission.objectives{
+        match objective {
+            Objective::Reach{ x, y, z } if !in_bounds(&(*x, *y, *z)) => {
+                return Err(format!("destino ({}, {}, {}) fora do mapa na missão '{}'", x, y, z, mission.name).into());
+            },
+            Objective::Destroy{ cells } => {
+                if let Some((x, y, z)) = cells.iter().find(|cell| !in_bounds(cell)) {
+                    return Err(format!("rocha ({}, {}, {}) fora do mapa na missão '{}'", x, y, z, mission.name).into());
+                }
+            },
+            _ => {},
+        }
+    }
+    Ok(mission)
+}
+
+fn load_missions() -> Vec<Mission> {
+    let Ok(entries) = fs::read_dir("assets/missions") else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries.filter_map(|en| en.ok()).map(|en| en.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let mut missions = Vec::new();
+    for path in paths{
+        match load_mission(&path.to_string_lossy()) {
+            Ok(mission) => missions.push(mission),
+            Err(e) => println!("Erro ao carregar a missão {}: {}", path.display(), e),
+        }
+    }
+    missions
+}
+
+fn choose_mission_menu() -> Mission {
+    let missions = load_missions();
+    if missions.len() <= 1 {
+        return missions.into_iter().next().unwrap_or_else(default_mission);
+    }
+
+    loop {
+        println!("\nEscolha a missão:");
+        for (index, mission) in missions.iter().enumerate(){
+            println!("{}. {} ({} objetivo(s))", index + 1, mission.name, mission.objectives.len());
+        }
+        let input = get_player_input();
+        match input.parse::<usize>() {
+            Ok(choice) if (1..=missions.len()).contains(&choice) => return missions[choice - 1].clone(),
+            _ => println!("Opção inválida"),
+        }
+    }
+}
+
+fn load_rules_presets(path: &str) -> Result<Vec<Rules>, Box<dyn std::error::Error>> {
+    let mut rules_csv = ReaderBuilder::new().delimiter(b';').from_path(path)?;
+    let mut presets = Vec::new();
+    for record in rules_csv.deserialize() {
+        presets.push(record?);
+    }
+    Ok(presets)
+}
+
+fn choose_rules_menu() -> Rules {
+    let presets = match load_rules_presets("assets/rules.csv") {
+        Ok(presets) if !presets.is_empty() => presets,
+        _ => {
+            println!("Não foi possível carregar as dificuldades, usando a dificuldade padrão...");
+            return Rules::default();
+        },
+    };
+
+    loop {
+        println!("\nEscolha a dificuldade:");
+        for (index, rules) in presets.iter().enumerate(){
+            println!("{}. {} (Oxigênio: {}, Torpedos: {} casas, Sonar: {}x{}, Colisões: {}% de dano)",
+                index + 1, rules.name, rules.starting_oxygen, rules.torpedo_range,
+                rules.scan_width as u16 * 2 + 1, rules.scan_height as u16 * 2 + 1, rules.collision_damage);
+        }
+        let input = get_player_input();
+        match input.parse::<usize>() {
+            Ok(choice) if (1..=presets.len()).contains(&choice) => return presets[choice - 1].clone(),
+            _ => println!("Opção inválida"),
+        }
+    }
+}
+
+fn update_save_file(game: &Game) -> std::io::Result<()>{
+    write_save_file(game)?;
+
+    println!("Jogo salvo com sucesso!");
+    Ok(())
+}
+
+fn write_save_file(game: &Game) -> std::io::Result<()>{
+    let save_name = format!("{}-{}.json", game.id, game.sub_name);
+    let filepath = format!("saves/{}", save_name);
+
+    let mut file = File::create(filepath)?;
+    let data = serde_json::to_string(&game)?;
+    file.write_all(data.as_bytes())?;
+    Ok(())
+}
+
+fn get_save_files() -> std::io::Result<Vec<String>> {
+    let entries = fs::read_dir("saves")?;
+
+    let reg = Regex::new(r"^\d+-").unwrap();
+
+    let mut match_files = Vec::new();
+
+    for en in entries {
+        let en = en?;
+        let path = en.path();
+
+        if path.is_file(){
+            if let Some(filename) = path.file_name(){
+                if let Some(filename_str) = filename.to_str(){
+                    if reg.is_match(filename_str){
+                        match_files.push(filename_str.to_string());
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(match_files)
+    
+}
+
+#[allow(unused_variables)]
+fn load_save_menu() ->std::io::Result<Option<Game>>{
+    let save_files = get_save_files()?;
+    let input = String::new();
+
+    for sf in &save_files {
+        match peek_save_file(sf) {
+            Ok(SaveSummary{ rules, outcome: Some(outcome), .. }) if rules.ironman => println!("{} ({}) [FINALIZADO - {}]", sf, rules.name, outcome.name()),
+            Ok(SaveSummary{ campaign_level: Some(level), .. }) => println!("{} (Campanha - nível {})", sf, level + 1),
+            Ok(summary) => println!("{} ({})", sf, summary.rules.name),
+            Err(_) => println!("{}", sf),
+        }
+    }
+
+    loop{
+        println!("\nEntre o nome do jogo que deseja carregar:");
+
+        let input = get_save_name_input(&save_files);
+        for sf in &save_files{
+            if sf.contains(&input) {
+                if let Ok(SaveSummary{ rules, outcome: Some(outcome), .. }) = peek_save_file(sf) {
+                    if rules.ironman {
+                        println!("A partida ironman {} já terminou em {} e não pode ser retomada!", sf, outcome.name().to_lowercase());
+                        return Ok(None);
+                    }
+                }
+                let game = load_save_file(sf)?;
+                return Ok(Some(game));
+            }
+        }
+        println!("Entrada inválida");
+    }
+
+}
+
+#[allow(unused_assignments)]
+fn delete_save_menu() -> Result<(), Box<dyn std::error::Error>>{
+    let save_files = get_save_files()?;
+    let mut input = String::new();
+    let mut confirmation_input = String::new();
+
+    for sf in &save_files {
+        println!("{}", sf);
+    }
+
+    loop{
+        println!("\nEntre o nome do jogo que deseja deletar:");
+
+        input = get_save_name_input(&save_files);
+        for sf in &save_files{
+            if sf.contains(&input) {
+                loop{
+                    println!("Jogo {} encontrado, desejar excluir o jogo salvo? Essa ação não pode ser desfeita. (s/n)", &sf);
+                    confirmation_input = get_player_input();
+                    match confirmation_input.as_str(){
+                        "s" => {
+                            delete_save_file(sf);
+                            return Ok(());
+                        },
+                        "n" => return Ok(()),
+                        _ => println!("Confirmação inválida"),
+                    }
+
+                }
+            }
+        }
+        println!("Entrada inválida");
+    }
+
+}
+
+fn load_save_file(save_name: &str) -> std::io::Result<Game> {
+
+    let filepath = format!("saves/{}", save_name);
+
+    let mut file = File::open(&filepath)?;
+
+    let mut data = String::new();
+    file.read_to_string(&mut data)?;
+
+    let loaded_game: Game = serde_json::from_str(&data)?;
+    
+    println!("Jogo {} carregado com sucesso!", save_name);
+    
+    Ok(loaded_game)
+}
+
+fn peek_save_file(save_name: &str) -> std::io::Result<SaveSummary> {
+    let data = fs::read_to_string(format!("saves/{}", save_name))?;
+    let summary: SaveSummary = serde_json::from_str(&data)?;
+    Ok(summary)
+}
+
+fn delete_save_file(save_name: &str){
+    let filepath = format!("saves/{}", save_name);
+
+    let _ = fs::remove_file(&filepath);
+    
+    println!("Jogo {} deletado com sucesso", save_name);
+}
+
+#[allow(unused_assignments)]
+fn load_map_csv(path: &str) -> Result<Vec<Vec<Vec<String>>>, Box<dyn std::error::Error>> {
+    let mut base_map = Reader::from_path(path)?;
+    let mut local_map: Vec<Vec<Vec<String>>> = vec![vec![vec!["".to_string(); 50]; 50];3];
+
+    let mut h_index = 0;
+
+    for (x_index, x_value) in base_map.records().enumerate() {
+        let row = x_value?;
+        h_index = x_index/50;
+
+        let items: Vec<String> = row.get(0).unwrap_or("").split(';')
+            .map(|s| s.to_string())
+            .collect();
+
+        for (y_index, y_value) in items.iter().enumerate() {
+            local_map[h_index][x_index%50][y_index] = y_value.to_string();
+        }
+    }
+    Ok(local_map)
+}
+
+fn load_currents_csv(path: &str) -> Result<Vec<Current>, Box<dyn std::error::Error>> {
+    if !Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut currents = Vec::new();
+    for (z, layer) in load_map_csv(path)?.iter().enumerate(){
+        for (y, row) in layer.iter().enumerate(){
+            for (x, tile) in row.iter().enumerate(){
+                let direction = match tile.get(..1) {
+                    Some("N") => Direction::North,
+                    Some("S") => Direction::South,
+                    Some("E") => Direction::East,
+                    Some("W") => Direction::West,
+                    _ => continue,
+                };
+                currents.push(Current{
+                    position: (x as u8, y as u8, z as u8),
+                    direction,
+                    strength: tile[1..].parse().unwrap_or(1),
+                    revealed: false,
+                });
+            }
+        }
+    }
+    Ok(currents)
+}
+
+fn load_zones_csv(path: &str) -> Result<Vec<Zone>, Box<dyn std::error::Error>> {
+    if !Path::new(path).exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut zones = Vec::new();
+    for (z, layer) in load_map_csv(path)?.iter().enumerate(){
+        for (y, row) in layer.iter().enumerate(){
+            for (x, tile) in row.iter().enumerate(){
+                let kind = match tile.as_str() {
+                    "vent" => ZoneKind::Vent,
+                    "dark" => ZoneKind::Dark,
+                    _ => continue,
+                };
+                zones.push(Zone{
+                    position: (x as u8, y as u8, z as u8),
+                    kind,
+                    revealed: false,
+                });
+            }
+        }
+    }
+    Ok(zones)
+}
+
+fn load_layer_rules(path: &str) -> Result<Vec<LayerRules>, Box<dyn std::error::Error>> {
+    let mut layers_csv = ReaderBuilder::new().delimiter(b';').from_path(path)?;
+    let mut layers = Vec::new();
+    for record in layers_csv.deserialize() {
+        layers.push(record?);
+    }
+    Ok(layers)
+}
+
+fn load_demo_csv() -> Result<Vec<String>, Box<dyn std::error::Error>>{
+    let mut demo_csv = Reader::from_path("assets/demo.csv")?;
+    let csv_rec = demo_csv.records().next().ok_or("Erro ao carregar demo.csv")??;
+    let demo_inputs: Vec<String> = csv_rec.get(0).unwrap_or("").split(';').map(|s| s.to_string()).collect();
+
+    Ok(demo_inputs)
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+struct KeyBinding{
+    key: String,
+    command: String,
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+struct Macro{
+    name: String,
+    commands: String,
+}
+
+#[derive(Deserialize, Serialize, Default, Clone)]
+struct Config{
+    #[serde(default)]
+    aliases: Vec<KeyBinding>,
+    #[serde(default)]
+    macros: Vec<Macro>,
+    #[serde(default)]
+    theme: Theme,
+    #[serde(default)]
+    accessible: bool,
+}
+
+struct CommandContext<'a>{
+    keymap: &'a [KeyBinding],
+    config: &'a Config,
+}
+
+fn load_keymap(path: &str) -> Result<Vec<KeyBinding>, Box<dyn std::error::Error>> {
+    let mut keymap_csv = ReaderBuilder::new().delimiter(b';').from_path(path)?;
+    let mut keymap = Vec::new();
+    for record in keymap_csv.deserialize() {
+        keymap.push(record?);
+    }
+    Ok(keymap)
+}
+
+#[derive(Clone)]
+struct Token{
+    text: String,
+    column: usize,
+    width: usize,
+}
+
+#[derive(Debug)]
+struct ParseError{
+    column: usize,
+    width: usize,
+    message: String,
+}
+
+const VERBS: [&str; 23] = ["move", "scan", "shoot", "load", "capture", "inventory", "use", "repair", "save",
+    "leaderboard", "stats", "shop", "buy", "undo", "redo", "map", "mark", "unmark", "waypoints", "go", "where", "help", "quit"];
+const MAP_OPTIONS: [&str; 2] = ["all", "plain"];
+const DIRECTIONS: [(&str, Direction); 6] = [("north", Direction::North), ("south", Direction::South),
+    ("east", Direction::East), ("west", Direction::West), ("up", Direction::Up), ("down", Direction::Down)];
+const SONAR_MODES: [(&str, SonarMode); 3] = [("short", SonarMode::Short), ("long", SonarMode::Long), ("vertical", SonarMode::Vertical)];
+const TORPEDOES: [(&str, Torpedo); 3] = [("standard", Torpedo::Standard), ("wide", Torpedo::Wide), ("depth", Torpedo::Depth)];
+const USABLE_ITEMS: [(&str, Item); 3] = [("oxygen", Item::OxygenTank), ("torpedoes", Item::TorpedoCrate), ("repair", Item::RepairKit)];
+const UPGRADES: [(&str, Upgrade); 4] = [("tank", Upgrade::OxygenTank), ("torpedo", Upgrade::TorpedoRange),
+    ("sonar", Upgrade::Sonar), ("hull", Upgrade::Hull)];
+const MACRO_COMMANDS: [&str; 4] = ["define", "run", "delete", "list"];
+const MAX_WAYPOINTS: usize = 9;
+const MAX_REPEATS: u8 = 20;
+const MAX_EXPANSION_DEPTH: usize = 8;
+const MAX_QUEUED_ACTIONS: usize = 100;
+
+fn tokenize(input: &str) -> Vec<Vec<Token>> {
+    let mut commands = vec![Vec::new()];
+    let mut current: Option<Token> = None;
+    for (column, c) in input.chars().enumerate(){
+        if c.is_whitespace() || c == ';' {
+            if let Some(token) = current.take(){
+                commands.last_mut().unwrap().push(token);
+            }
+            if c == ';' {
+                commands.push(Vec::new());
+            }
+        }else{
+            let token = current.get_or_insert(Token{ text: String::new(), column, width: 0 });
+            token.text.extend(c.to_lowercase());
+            token.width += 1;
+        }
+    }
+    if let Some(token) = current{
+        commands.last_mut().unwrap().push(token);
+    }
+    commands.retain(|tokens| !tokens.is_empty());
+    commands
+}
+
+fn expand_keymap(tokens: Vec<Token>, keymap: &[KeyBinding]) -> Vec<Token> {
+    match keymap.iter().find(|binding| binding.key.to_lowercase() == tokens[0].text){
+        Some(binding) => {
+            let mut expanded: Vec<Token> = binding.command.split_whitespace()
+                .map(|text| Token{ text: text.to_lowercase(), ..tokens[0].clone() })
+                .collect();
+            expanded.extend(tokens.into_iter().skip(1));
+            expanded
+        }
+        None => tokens,
+    }
+}
+
+fn resolve_token<T: Copy>(token: &Token, options: &[(&str, T)], what: &str) -> Result<T, ParseError> {
+    if let Some((_, value)) = options.iter().find(|(name, _)| *name == token.text){
+        return Ok(*value);
+    }
+    let matches: Vec<&(&str, T)> = options.iter().filter(|(name, _)| name.starts_with(&token.text)).collect();
+    let names = |list: Vec<&str>| list.join(", ");
+    match matches.as_slice() {
+        [(_, value)] => Ok(*value),
+        [] => Err(ParseError{ column: token.column, width: token.width,
+            message: format!("Não reconheço '{}' como {}. Opções: {}", token.text, what, names(options.iter().map(|(name, _)| *name).collect())) }),
+        _ => Err(ParseError{ column: token.column, width: token.width,
+            message: format!("'{}' é ambíguo como {}, pode ser: {}", token.text, what, names(matches.iter().map(|(name, _)| *name).collect())) }),
+    }
+}
+
+fn parse_map_command(args: &[Token]) -> Result<Action, ParseError> {
+    let mut view = MapView::Current;
+    let mut show_markers = true;
+    for token in args{
+        let unexpected = || ParseError{ column: token.column, width: token.width,
+            message: format!("Argumento inesperado '{}' para o comando 'map'", token.text) };
+        if let Ok(layer) = token.text.parse::<u8>() {
+            if !matches!(view, MapView::Current) {
+                return Err(unexpected());
+            }
+            view = MapView::Layer(layer);
+            continue;
+        }
+        match resolve_token(token, &MAP_OPTIONS.map(|option| (option, option)), "opção do mapa")? {
+            "all" if matches!(view, MapView::Current) => view = MapView::All,
+            "plain" if show_markers => show_markers = false,
+            _ => return Err(unexpected()),
+        }
+    }
+    Ok(Action::Map(view, show_markers))
+}
+
+fn parse_mark_command(verb: &Token, args: &[Token]) -> Result<Action, ParseError> {
+    let (coordinates, label) = match args {
+        [label] => (None, label),
+        [x, y, z, label] => {
+            let coordinate = |token: &Token| token.text.parse::<u8>().map_err(|_| ParseError{ column: token.column, width: token.width,
+                message: format!("Coordenada inválida '{}', use números (ex: mark 10 20 1 tesouro)", token.text) });
+            (Some((coordinate(x)?, coordinate(y)?, coordinate(z)?)), label)
+        }
+        [] => return Err(ParseError{ column: verb.column + verb.width, width: 1,
+            message: "Informe o nome do marcador: mark <nome> ou mark <x> <y> <z> <nome>".to_string() }),
+        _ => return Err(ParseError{ column: args[1].column, width: args[1].width,
+            message: "Use mark <nome> ou mark <x> <y> <z> <nome>, com o nome em uma única palavra".to_string() }),
+    };
+    if !valid_name(&label.text) || label.text.chars().all(|c| c.is_ascii_digit()) {
+        return Err(ParseError{ column: label.column, width: label.width,
+            message: format!("Nome de marcador inválido '{}', use letras, números, '_' ou '-'", label.text) });
+    }
+    Ok(Action::Mark(coordinates, label.text.clone()))
+}
+
+fn parse_command(tokens: &[Token]) -> Result<(Action, u8), ParseError> {
+    let mut args = tokens;
+    let mut repeats = 1;
+    match resolve_token(&args[0], &VERBS.map(|verb| (verb, verb)), "comando") {
+        Ok("map") => return Ok((parse_map_command(&args[1..])?, 1)),
+        Ok("mark") => return Ok((parse_mark_command(&args[0], &args[1..])?, 1)),
+        _ => {}
+    }
+    if let Some(last) = args.last().filter(|token| args.len() > 1 && token.text.chars().all(|c| c.is_ascii_digit())){
+        repeats = match last.text.parse::<u8>() {
+            Ok(count) if (1..=MAX_REPEATS).contains(&count) => count,
+            _ => return Err(ParseError{ column: last.column, width: last.width,
+                message: format!("Número de repetições inválido '{}', use de 1 a {}", last.text, MAX_REPEATS) }),
+        };
+        args = &args[..args.len() - 1];
+    }
+
+    let verb = resolve_token(&args[0], &VERBS.map(|verb| (verb, verb)), "comando")?;
+    let argument = args.get(1);
+    let expected = match verb {
+        "move" | "load" | "use" | "buy" | "unmark" | "go" => 1,
+        "scan" | "shoot" => argument.map_or(0, |_| 1),
+        _ => 0,
+    };
+    if let Some(extra) = args.get(expected + 1){
+        return Err(ParseError{ column: extra.column, width: extra.width,
+            message: format!("Argumento inesperado '{}' para o comando '{}'", extra.text, verb) });
+    }
+    let argument = match (expected, argument) {
+        (1, Some(argument)) => argument,
+        (1, None) => return Err(ParseError{ column: args[0].column + args[0].width, width: 1,
+            message: format!("O comando '{}' precisa de uma opção, digite 'Help' para ver as opções", verb) }),
+        _ => &args[0],
+    };
+
+    let action = match verb {
+        "move" => Action::Move(resolve_token(argument, &DIRECTIONS, "direção")?),
+        "scan" if expected == 1 => Action::Scan(resolve_token(argument, &SONAR_MODES, "modo de sonar")?),
+        "scan" => Action::Scan(SonarMode::Short),
+        "shoot" if expected == 1 => Action::Shoot(Some(resolve_token(argument, &TORPEDOES, "torpedo")?)),
+        "shoot" => Action::Shoot(None),
+        "load" => Action::Load(resolve_token(argument, &TORPEDOES, "torpedo")?),
+        "use" => Action::Use(resolve_token(argument, &USABLE_ITEMS, "item")?),
+        "buy" => Action::Buy(resolve_token(argument, &UPGRADES, "melhoria")?),
+        "capture" => Action::Capture,
+        "inventory" => Action::Inventory,
+        "repair" => Action::Repair,
+        "save" => Action::Save,
+        "leaderboard" => Action::Leaderboard,
+        "stats" => Action::Stats,
+        "shop" => Action::Shop,
+        "unmark" => Action::Unmark(argument.text.clone()),
+        "waypoints" => Action::Waypoints,
+        "go" => Action::Go(argument.text.clone()),
+        "where" => Action::Where,
+        "undo" => Action::Undo,
+        "redo" => Action::Redo,
+        "help" => Action::Help,
+        _ => Action::Quit,
+    };
+    Ok((action, repeats))
+}
+
+fn expansion_error(token: &Token, name: &str, error: ParseError) -> ParseError {
+    ParseError{ column: token.column, width: token.width, message: format!("Em '{}': {}", name, error.message) }
+}
+
+fn expand_user_command(tokens: &[Token], context: &CommandContext, stack: &mut Vec<String>) -> Result<Option<Vec<Action>>, ParseError> {
+    let (name, expansion, rest) = if tokens[0].text == "macro" && tokens.get(1).is_some_and(|token| token.text == "run") {
+        let Some(name) = tokens.get(2) else {
+            return Err(ParseError{ column: tokens[1].column + tokens[1].width, width: 1,
+                message: "Informe o nome do macro: macro run <nome>".to_string() });
+        };
+        if let Some(extra) = tokens.get(3){
+            return Err(ParseError{ column: extra.column, width: extra.width,
+                message: format!("Argumento inesperado '{}' para o comando 'macro run'", extra.text) });
+        }
+        match context.config.macros.iter().find(|m| m.name == name.text) {
+            Some(m) => (name, m.commands.clone(), &tokens[3..]),
+            None => return Err(ParseError{ column: name.column, width: name.width,
+                message: format!("Macro '{}' não definido, use 'macro define {} <comandos>'", name.text, name.text) }),
+        }
+    }else{
+        match context.config.aliases.iter().find(|alias| alias.key == tokens[0].text) {
+            Some(alias) => (&tokens[0], alias.command.clone(), &tokens[1..]),
+            None => return Ok(None),
+        }
+    };
+
+    if stack.contains(&name.text) || stack.len() >= MAX_EXPANSION_DEPTH {
+        return Err(ParseError{ column: name.column, width: name.width,
+            message: format!("'{}' chama a si mesmo recursivamente ({} -> {})", name.text, stack.join(" -> "), name.text) });
+    }
+    let expansion = rest.iter().fold(expansion, |line, token| line + " " + &token.text);
+    stack.push(name.text.clone());
+    let actions = parse_commands(&expansion, context, stack).map_err(|e| expansion_error(name, &name.text, e));
+    stack.pop();
+    actions.map(Some)
+}
+
+fn parse_commands(input: &str, context: &CommandContext, stack: &mut Vec<String>) -> Result<Vec<Action>, ParseError> {
+    let mut actions = Vec::new();
+    for tokens in tokenize(input){
+        if let Some(expanded) = expand_user_command(&tokens, context, stack)? {
+            actions.extend(expanded);
+        }else{
+            let (action, repeats) = parse_command(&expand_keymap(tokens.clone(), context.keymap))?;
+            actions.extend(std::iter::repeat_n(action, repeats as usize));
+        }
+        if actions.len() > MAX_QUEUED_ACTIONS {
+            return Err(ParseError{ column: tokens[0].column, width: tokens[0].width,
+                message: format!("Comandos demais em uma linha, o limite é {}", MAX_QUEUED_ACTIONS) });
+        }
+    }
+    Ok(actions)
+}
+
+fn parse_player_input(input: &str, context: &CommandContext) -> Result<Vec<Action>, ParseError> {
+    parse_commands(input, context, &mut Vec::new())
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty() && !VERBS.contains(&name) && !["macro", "alias", "theme"].contains(&name) && name.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+fn print_user_commands(config: &Config){
+    if config.aliases.is_empty() && config.macros.is_empty() {
+        println!("Nenhum atalho ou macro definido");
+        return;
+    }
+    for alias in &config.aliases{
+        println!("alias {} = {}", alias.key, alias.command);
+    }
+    for m in &config.macros{
+        println!("macro {} = {}", m.name, m.commands);
+    }
+}
+
+fn skip_words(input: &str, count: usize) -> &str {
+    let mut rest = input.trim_start();
+    for _ in 0..count{
+        rest = rest.find(char::is_whitespace).map_or("", |end| rest[end..].trim_start());
+    }
+    rest.trim_end()
+}
+
+fn handle_config_command(input: &str, config: &mut Config, keymap: &[KeyBinding]) -> bool {
+    let mut words = input.split_whitespace();
+    let command = words.next().unwrap_or("").to_lowercase();
+    let subcommand = words.next().map(|word| word.to_lowercase());
+    let (is_macro, name, body) = match (command.as_str(), subcommand.as_deref()) {
+        ("theme", None) => {
+            println!("Tema atual: {}. Temas disponíveis: {}", config.theme.name(),
+                THEMES.iter().map(|(name, theme)| format!("{} ({})", name, theme.name())).collect::<Vec<_>>().join(", "));
+            return true;
+        }
+        ("theme", Some(name)) => {
+            match THEMES.iter().find(|(option, _)| option.starts_with(name)) {
+                Some((_, theme)) => {
+                    config.theme = *theme;
+                    save_user_config(config);
+                    println!("Tema alterado para: {}", theme.name());
+                }
+                None => println!("Tema desconhecido '{}', use: {}", name, THEMES.map(|(name, _)| name).join(", ")),
+            }
+            return true;
+        }
+        ("alias", None) | ("macro", Some("list")) => {
+            print_user_commands(config);
+            return true;
+        }
+        ("alias", Some(name)) => (false, name.to_string(), skip_words(input, 2).to_string()),
+        ("macro", Some("define")) => {
+            let name = words.next().unwrap_or("").to_lowercase();
+            let body = skip_words(input, 3).to_string();
+            (true, name, body)
+        }
+        ("macro", Some("delete")) => {
+            let name = words.next().unwrap_or("").to_lowercase();
+            let before = config.macros.len();
+            config.macros.retain(|m| m.name != name);
+            if config.macros.len() == before {
+                println!("Macro '{}' não definido", name);
+            }else{
+                println!("Macro '{}' removido", name);
+                save_user_config(config);
+            }
+            return true;
+        }
+        ("macro", Some("run")) => return false,
+        ("macro", _) => {
+            println!("Uso: macro define <nome> <comandos>, macro run <nome>, macro delete <nome> ou macro list");
+            return true;
+        }
+        _ => return false,
+    };
+
+    if !valid_name(&name) {
+        println!("Nome inválido '{}', use letras, números, '_' ou '-' e evite nomes de comandos", name);
+        return true;
+    }
+    let mut updated = config.clone();
+    if is_macro {
+        updated.macros.retain(|m| m.name != name);
+        updated.macros.push(Macro{ name: name.clone(), commands: body.clone() });
+    }else if body.is_empty() {
+        if config.aliases.iter().any(|alias| alias.key == name) {
+            config.aliases.retain(|alias| alias.key != name);
+            println!("Atalho '{}' removido", name);
+            save_user_config(config);
+        }else{
+            println!("Atalho '{}' não definido, use 'alias {} <comando>'", name, name);
+        }
+        return true;
+    }else{
+        updated.aliases.retain(|alias| alias.key != name);
+        updated.aliases.push(KeyBinding{ key: name.clone(), command: body.clone() });
+    }
+
+    let check = if is_macro { format!("macro run {}", name) } else { name.clone() };
+    let context = CommandContext{ keymap, config: &updated };
+    match parse_player_input(&check, &context) {
+        Ok(actions) if actions.is_empty() => println!("A definição de '{}' está vazia", name),
+        Ok(_) => {
+            *config = updated;
+            save_user_config(config);
+            println!("{} '{}' definido: {}", if is_macro { "Macro" } else { "Atalho" }, name, body);
+        }
+        Err(error) => {
+            println!("Definição inválida para '{}': {}", name, error.message);
+        }
+    }
+    true
+}
+
+fn load_user_config() -> std::io::Result<Config> {
+    if !Path::new("saves/config.json").exists() {
+        return Ok(Config::default());
+    }
+    let data = fs::read_to_string("saves/config.json")?;
+    let config: Config = serde_json::from_str(&data)?;
+    Ok(config)
+}
+
+fn save_user_config(config: &Config){
+    let result = File::create("saves/config.json").and_then(|mut file| {
+        let data = serde_json::to_string(config)?;
+        file.write_all(data.as_bytes())
+    });
+    if let Err(e) = result {
+        println!("Falha ao salvar as configurações: {}", e);
+    }
+}
+
+fn print_parse_error(input: &str, error: &ParseError, accessible: bool){
+    if accessible {
+        let text: String = input.chars().skip(error.column).take(error.width).collect();
+        match text.trim() {
+            "" => println!("Erro no fim do comando: {}.", error.message),
+            text => println!("Erro no caractere {}, em '{}': {}.", error.column + 1, text, error.message),
+        }
+        return;
+    }
+    println!("{}", input);
+    println!("{}{}", " ".repeat(error.column), "^".repeat(error.width));
+    println!("{}", error.message);
+}
+
+const HISTORY_PATH: &str = "saves/history.txt";
+
+enum Completion{
+    Nothing,
+    Saves(Vec<String>),
+    Commands{ keymap: Vec<KeyBinding>, config: Config, waypoints: Vec<String> },
+}
+
+struct PromptHelper{
+    completion: Completion,
+}
+
+fn option_names<T>(options: &[(&'static str, T)]) -> Vec<String> {
+    options.iter().map(|(name, _)| name.to_string()).collect()
+}
+
+impl PromptHelper{
+    fn candidates(&self, previous: &[String]) -> Vec<String> {
+        let (keymap, config, waypoints) = match &self.completion {
+            Completion::Nothing => return Vec::new(),
+            Completion::Saves(saves) => return saves.clone(),
+            Completion::Commands{ keymap, config, waypoints } => (keymap, config, waypoints),
+        };
+        let mut words: Vec<String> = previous.to_vec();
+        if let Some(binding) = words.first().and_then(|first| keymap.iter().find(|binding| binding.key == *first)) {
+            let mut expanded: Vec<String> = binding.command.split_whitespace().map(str::to_lowercase).collect();
+            expanded.extend(words.drain(1..));
+            words = expanded;
+        }
+        let verb = words.first().and_then(|first| VERBS.iter().find(|verb| verb.starts_with(first.as_str())));
+        match (words.len(), words.first().map(String::as_str), verb) {
+            (0, _, _) => VERBS.iter().map(|verb| verb.to_string())
+                .chain(["macro".to_string(), "alias".to_string(), "theme".to_string()])
+                .chain(keymap.iter().map(|binding| binding.key.clone()))
+                .chain(config.aliases.iter().map(|alias| alias.key.clone()))
+                .collect(),
+            (1, Some("macro"), _) => MACRO_COMMANDS.iter().map(|command| command.to_string()).collect(),
+            (1, Some("theme"), _) => option_names(&THEMES),
+            (2, Some("macro"), _) if words[1] == "run" || words[1] == "delete" => config.macros.iter().map(|m| m.name.clone()).collect(),
+            (1, _, Some(&"move")) => option_names(&DIRECTIONS),
+            (1, _, Some(&"scan")) => option_names(&SONAR_MODES),
+            (1, _, Some(&"shoot")) | (1, _, Some(&"load")) => option_names(&TORPEDOES),
+            (1, _, Some(&"use")) => option_names(&USABLE_ITEMS),
+            (1, _, Some(&"buy")) => option_names(&UPGRADES),
+            (1, _, Some(&"map")) => MAP_OPTIONS.iter().map(|option| option.to_string()).collect(),
+            (1, _, Some(&"go")) | (1, _, Some(&"unmark")) => waypoints.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Completer for PromptHelper{
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        let start = before.rfind(|c: char| c.is_whitespace() || c == ';').map_or(0, |index| index + 1);
+        let word = before[start..].to_lowercase();
+        let segment = before[..start].rsplit(';').next().unwrap_or("");
+        let previous: Vec<String> = segment.split_whitespace().map(str::to_lowercase).collect();
+        let mut matches: Vec<String> = self.candidates(&previous).into_iter()
+            .filter(|candidate| candidate.to_lowercase().starts_with(&word))
+            .collect();
+        matches.sort();
+        matches.dedup();
+        Ok((start, matches))
+    }
+}
+
+impl Hinter for PromptHelper{
+    type Hint = String;
+}
+
+impl Highlighter for PromptHelper{}
+
+impl Validator for PromptHelper{}
+
+impl Helper for PromptHelper{}
+
+thread_local! {
+    static PROMPT: RefCell<Option<Editor<PromptHelper, FileHistory>>> = const { RefCell::new(None) };
+}
+
+fn read_prompt(prompt: &str, completion: Completion, remember: bool) -> rustyline::Result<String> {
+    PROMPT.with(|cell| {
+        let mut cell = cell.borrow_mut();
+        if cell.is_none() {
+            let mut editor = Editor::new()?;
+            let _ = editor.load_history(HISTORY_PATH);
+            *cell = Some(editor);
+        }
+        let editor = cell.as_mut().unwrap();
+        editor.set_helper(Some(PromptHelper{ completion }));
+        let input = editor.readline(prompt)?.trim().to_string();
+        if remember && !input.is_empty() && editor.add_history_entry(input.as_str())? {
+            let _ = editor.append_history(HISTORY_PATH);
+        }
+        Ok(input)
+    })
+}
+
+fn read_menu_input(completion: Completion) -> String {
+    match read_prompt("", completion, false) {
+        Ok(input) => input,
+        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => {
+            println!("Encerrando o jogo...");
+            std::process::exit(0);
+        }
+        Err(e) => panic!("Falha ao ler entrada: {}", e),
+    }
+}
+
+fn get_player_input() -> String {
+    read_menu_input(Completion::Nothing)
+}
+
+fn get_save_name_input(save_files: &[String]) -> String {
+    read_menu_input(Completion::Saves(save_files.to_vec()))
+}
+
+fn get_command_input(keymap: &[KeyBinding], config: &Config, game: &Game) -> Option<String> {
+    let waypoints = game.waypoints.iter().map(|waypoint| waypoint.label.clone()).collect();
+    let completion = Completion::Commands{ keymap: keymap.to_vec(), config: config.clone(), waypoints };
+    match read_prompt("> ", completion, true) {
+        Ok(input) => Some(input),
+        Err(ReadlineError::Interrupted) | Err(ReadlineError::Eof) => None,
+        Err(e) => panic!("Falha ao ler entrada: {}", e),
+    }
+}
+
+fn confirm_quit(game: &mut Game){
+    println!();
+    if game.rules.ironman {
+        println!("O jogo ironman é salvo automaticamente, encerrando...");
+        return;
+    }
+    println!("Deseja salvar o jogo antes de sair? (s/n)");
+    match read_prompt("", Completion::Nothing, false).as_deref() {
+        Ok("s") => {
+            game.last_save = Utc::now();
+            let _ = update_save_file(game);
+        }
+        _ => println!("Encerrando o jogo sem salvar..."),
+    }
+}
+
+fn title_screen(){
+    print_achievements();
+    loop{
+        let accessible = load_user_config().unwrap_or_default().accessible;
+        println!("\n1. Novo jogo \n2. Carregar jogo salvo\n3. Demo Game\n4. Deletar um jogo salvo\n5. Placar\n6. Desafio diário\n7. Contra o relógio\n8. Campanha\n9. Modo acessível para leitores de tela ({})\n10. Sair",
+            if accessible { "ligado" } else { "desligado" });
+        let input = get_player_input();
+        match input.as_str() {
+            "1" =>{
+                let _ = create_save_file(GameMode::Standard);
+                break
+            },
+            "2" => {
+                match load_save_menu(){
+                    Ok(Some(game)) =>{
+                        game_loop(game, false);
+                        break
+                    },
+                    Ok(None) => {},
+                    Err(e) =>{
+                        println!("Erro ao carregar o jogo: {}", e);
+                    },
+                }
+            },
+            "3" =>{
+                let demo_game = new_game("Demo".to_string(), Rules::default(), "assets/base_map.csv").expect("Falha ao carregar mapa");
+
+                game_loop(demo_game, true);
+            },
+            "4" =>{
+                let _ = delete_save_menu();
+            },
+            "5" =>{
+                print_leaderboard();
+            },
+            "6" =>{
+                let mut profile = load_profile().unwrap_or_default();
+                if start_daily_challenge(&mut profile){
+                    let _ = create_save_file(GameMode::Daily);
+                    break
+                }
+            },
+            "7" =>{
+                let _ = create_save_file(GameMode::TimeAttack);
+                break
+            },
+            "8" =>{
+                let _ = create_save_file(GameMode::Campaign);
+                break
+            },
+            "9" =>{
+                set_accessible_mode(!accessible);
+            },
+            "10" =>break,
+            _ =>println!("Opção inválida"),
+        }
+    }
+
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tile{
+    Water,
+    Unexplored,
+    Rock,
+    BorderRock,
+    Player,
+    Treasure,
+    Item,
+    Creature,
+    Vent,
+    Dark,
+    Current,
+    Marker,
+    Trail,
+}
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+enum Theme{
+    #[default]
+    Classic,
+    HighContrast,
+    Colorblind,
+    Plain,
+}
+
+const THEMES: [(&str, Theme); 4] = [("classic", Theme::Classic), ("contrast", Theme::HighContrast),
+    ("colorblind", Theme::Colorblind), ("plain", Theme::Plain)];
+
+impl Theme{
+    fn name(&self) -> &'static str {
+        match self {
+            Theme::Classic => "Clássico",
+            Theme::HighContrast => "Alto contraste",
+            Theme::Colorblind => "Daltonismo",
+            Theme::Plain => "Sem cores",
+        }
+    }
+
+    fn foreground(&self, tile: Tile) -> &'static str {
+        match (self, tile) {
+            (Theme::Plain, _) => "",
+            (_, Tile::Water) | (_, Tile::Unexplored) => "39",
+            (Theme::Classic, Tile::Rock) => "38;5;179",
+            (Theme::Classic, Tile::BorderRock) => "38;5;250",
+            (Theme::Classic, Tile::Player) => "1;97",
+            (Theme::Classic, Tile::Treasure) => "1;93",
+            (Theme::Classic, Tile::Item) => "92",
+            (Theme::Classic, Tile::Creature) => "1;91",
+            (Theme::Classic, Tile::Vent) => "38;5;208",
+            (Theme::Classic, Tile::Dark) => "90",
+            (Theme::Classic, Tile::Current) => "96",
+            (Theme::Classic, Tile::Marker) => "1;95",
+            (Theme::Classic, Tile::Trail) => "93",
+            (Theme::HighContrast, Tile::Rock) => "1;93",
+            (Theme::HighContrast, Tile::BorderRock) => "1;30",
+            (Theme::HighContrast, Tile::Player) => "1;30",
+            (Theme::HighContrast, Tile::Dark) => "1;37",
+            (Theme::HighContrast, _) => "1;97",
+            (Theme::Colorblind, Tile::Rock) => "38;5;214",
+            (Theme::Colorblind, Tile::BorderRock) => "38;5;252",
+            (Theme::Colorblind, Tile::Player) => "1;97",
+            (Theme::Colorblind, Tile::Treasure) => "1;38;5;227",
+            (Theme::Colorblind, Tile::Item) => "38;5;117",
+            (Theme::Colorblind, Tile::Creature) => "1;38;5;202",
+            (Theme::Colorblind, Tile::Vent) => "38;5;175",
+            (Theme::Colorblind, Tile::Dark) => "38;5;244",
+            (Theme::Colorblind, Tile::Current) => "38;5;117",
+            (Theme::Colorblind, Tile::Marker) => "1;38;5;227",
+            (Theme::Colorblind, Tile::Trail) => "38;5;214",
+        }
+    }
+
+    fn background(&self, tile: Tile, layer: u8) -> &'static str {
+        match (self, tile) {
+            (Theme::Plain, _) => "",
+            (Theme::Classic, Tile::Unexplored) | (Theme::Colorblind, Tile::Unexplored) => "48;5;236",
+            (Theme::Classic, Tile::BorderRock) | (Theme::Colorblind, Tile::BorderRock) => "48;5;240",
+            (Theme::HighContrast, Tile::Unexplored) => "40",
+            (Theme::HighContrast, Tile::BorderRock) => "107",
+            (Theme::HighContrast, Tile::Player) => "103",
+            (Theme::HighContrast, _) => "44",
+            (Theme::Classic, _) => match layer {
+                0 => "48;5;31",
+                1 => "48;5;24",
+                _ => "48;5;17",
+            },
+            (Theme::Colorblind, _) => match layer {
+                0 => "48;5;25",
+                1 => "48;5;18",
+                _ => "48;5;16",
+            },
+        }
+    }
+
+    fn paint(&self, tile: Tile, layer: u8, text: &str) -> String {
+        if *self == Theme::Plain {
+            return text.to_string();
+        }
+        format!("\x1b[{};{}m{}\x1b[0m", self.foreground(tile), self.background(tile, layer), text)
+    }
+}
+
+fn active_theme(config: &Config) -> Theme {
+    use std::io::IsTerminal;
+    if config.accessible || std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty()) || !std::io::stdout().is_terminal() {
+        return Theme::Plain;
+    }
+    config.theme
+}
+
+fn tile_glyph(game: &Game, x: u8, y: u8, z: u8) -> (char, Tile) {
+    if let Some(creature) = game.creatures.iter().find(|c| c.spotted && c.position == (x, y, z)){
+        return match creature.kind {
+            CreatureKind::Eel => ('~', Tile::Creature),
+            CreatureKind::Squid => ('@', Tile::Creature),
+        };
+    }
+    if let Some(zone) = zone_at(game, (x, y, z)).filter(|zone| zone.revealed && zone.position != game.player_position){
+        return match zone.kind {
+            ZoneKind::Vent => ('%', Tile::Vent),
+            ZoneKind::Dark => ('░', Tile::Dark),
+        };
+    }
+    match game.player_map[z as usize][y as usize][x as usize].as_str(){
+        "borderRock" => ('#', Tile::BorderRock),
+        "rock" => ('#', Tile::Rock),
+        "n/a" => match current_at(game, (x, y, z)) {
+            Some(current) if current.revealed => match current.direction {
+                Direction::North => ('↑', Tile::Current),
+                Direction::South => ('↓', Tile::Current),
+                Direction::East => ('→', Tile::Current),
+                _ => ('←', Tile::Current),
+            },
+            _ => (' ', Tile::Water),
+        },
+        "treasure" => ('*', Tile::Treasure),
+        "torpedoCrate" => ('+', Tile::Item),
+        "oxygenTank" => ('o', Tile::Item),
+        "repairKit" => ('r', Tile::Item),
+        "pearl" | "goldCoins" => ('$', Tile::Treasure),
+        "artifact" => ('&', Tile::Treasure),
+        "player" => match game.player_direction {
+            Direction::South => ('v', Tile::Player),
+            Direction::East => ('>', Tile::Player),
+            Direction::West => ('<', Tile::Player),
+            _ => ('^', Tile::Player),
+        },
+        "" => (' ', Tile::Unexplored),
+        _ => (' ', Tile::Water),
+    }
+}
+
+fn compass_strip(direction: Direction) -> String {
+    let points = ["N", "NE", "L", "SE", "S", "SO", "O", "NO"];
+    let center = match direction {
+        Direction::East => 2,
+        Direction::South => 4,
+        Direction::West => 6,
+        _ => 0,
+    };
+    (center + 6..=center + 10)
+        .map(|index| match index % 8 {
+            point if point == center => format!("[{}]", points[point]),
+            point => points[point].to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" - ")
+}
+
+fn print_position(game: &Game){
+    let (x, y, z) = game.player_position;
+    println!("Posição: x {} | y {} | nível {} ({}) | Rumo: {} | Percorrido: {} casas",
+        x, y, z, layer_rules(game, z).name, game.player_direction.name(), game.stats.distance);
+    println!("Bússola: {}", compass_strip(game.player_direction));
+}
+
+fn describe_position(game: &Game) -> String {
+    let (x, y, z) = game.player_position;
+    format!("Posição x {}, y {}, nível {}, {}. Rumo {}.", x, y, z, layer_rules(game, z).name, game.player_direction.name().to_lowercase())
+}
+
+fn describe_path(game: &Game, direction: Direction) -> String {
+    let (x, y, z) = game.player_position;
+    if matches!(direction, Direction::Up | Direction::Down) {
+        let next_z = if direction == Direction::Up { z.checked_sub(1) } else { Some(z + 1).filter(|next| (*next as usize) < game.player_map.len()) };
+        return match next_z.map(|next_z| game.player_map[next_z as usize][y as usize][x as usize].as_str()) {
+            None if direction == Direction::Up => "superfície".to_string(),
+            None => "fundo do oceano".to_string(),
+            Some("rock") | Some("borderRock") => "rocha".to_string(),
+            Some("") => "inexplorado".to_string(),
+            Some(_) => "livre".to_string(),
+        };
+    }
+    let (dx, dy) = direction_offset(&direction);
+    let (range_x, range_y) = scan_window(game);
+    let range = if dx != 0 { range_x } else { range_y } as i16;
+    let mut free = 0;
+    for step in 1..=range{
+        let (next_x, next_y) = (x as i16 + dx as i16 * step, y as i16 + dy as i16 * step);
+        let ending = if next_x < 0 || next_y < 0 || next_x as usize >= game.player_map[0][0].len() || next_y as usize >= game.player_map[0].len() {
+            "a borda do mapa"
+        }else{
+            match game.player_map[z as usize][next_y as usize][next_x as usize].as_str() {
+                "rock" | "borderRock" => "rocha",
+                "" => "área inexplorada",
+                _ => {
+                    free += 1;
+                    continue;
+                }
+            }
+        };
+        return match free {
+            0 => format!("{} adjacente", ending),
+            _ => format!("livre por {} casa(s), depois {}", free, ending),
+        };
+    }
+    format!("livre por mais de {} casas", free)
+}
+
+fn accessible_hud(game: &Game, previous: Option<(u16, u8)>){
+    let vitals = |value: u16, before: Option<u16>, unit: &str| match before {
+        Some(before) if before > value => format!("{}{}, menos {}", value, unit, before - value),
+        Some(before) if before < value => format!("{}{}, mais {}", value, unit, value - before),
+        _ => format!("{}{}", value, unit),
+    };
+    println!("\nTurno {}. {}", game.stats.turns + 1, describe_position(game));
+    println!("Oxigênio {}. Casco {}. Créditos {}.", vitals(game.oxygen, previous.map(|p| p.0), ""),
+        vitals(game.hull.into(), previous.map(|p| p.1.into()), "%"), game.credits);
+    if let Some(limit) = game.time_limit_ms {
+        let remaining = limit.saturating_sub(game.elapsed_ms) / 1000;
+        println!("Tempo restante: {} minutos e {} segundos.", remaining / 60, remaining % 60);
+    }
+
+    let directions = [(Direction::North, "Norte"), (Direction::South, "Sul"), (Direction::East, "Leste"),
+        (Direction::West, "Oeste"), (Direction::Up, "Acima"), (Direction::Down, "Abaixo")];
+    let paths: Vec<String> = directions.iter().map(|(direction, name)| format!("{}: {}", name, describe_path(game, *direction))).collect();
+    println!("{}.", paths.join(". "));
+
+    let (player_x, player_y, player_z) = game.player_position;
+    let (range_x, range_y) = scan_window(game);
+    let mut detected: Vec<(u16, String)> = Vec::new();
+    for y in player_y.saturating_sub(range_y)..=(player_y + range_y).min(game.player_map[0].len() as u8 - 1){
+        for x in player_x.saturating_sub(range_x)..=(player_x + range_x).min(game.player_map[0][0].len() as u8 - 1){
+            if let Some(item) = Item::from_tile(&game.player_map[player_z as usize][y as usize][x as usize]) {
+                let distance = player_x.abs_diff(x) as u16 + player_y.abs_diff(y) as u16;
+                detected.push((distance, format!("{} {}", item.name(), relative_bearing(game.player_position, (x, y, player_z)))));
+            }
+        }
+    }
+    for creature in game.creatures.iter().filter(|c| c.spotted && c.position.2 == player_z && in_sonar_window(game, c.position)){
+        let distance = player_x.abs_diff(creature.position.0) as u16 + player_y.abs_diff(creature.position.1) as u16;
+        detected.push((distance, format!("{} {}", creature.kind.name(), relative_bearing(game.player_position, creature.position))));
+    }
+    for waypoint in game.waypoints.iter().filter(|waypoint| waypoint.position != game.player_position){
+        detected.push((u16::MAX, format!("marcador {}, {}", waypoint.label, waypoint_distance(game, waypoint))));
+    }
+    detected.sort_by_key(|(distance, _)| *distance);
+    if !detected.is_empty() {
+        println!("Detectado: {}.", detected.into_iter().map(|(_, description)| description).collect::<Vec<_>>().join("; "));
+    }
+    if let Some(current) = current_at(game, game.player_position).filter(|current| current.revealed) {
+        println!("Correnteza para {} nesta posição.", current.direction.name().to_lowercase());
+    }
+
+    for (index, objective) in game.mission.objectives.iter().enumerate(){
+        if !game.objectives_done.get(index).copied().unwrap_or(false) {
+            let (current, target) = objective_progress(game, objective);
+            match target {
+                1 => println!("Objetivo: {}.", objective_description(game, objective)),
+                _ => println!("Objetivo: {}, {} de {}.", objective_description(game, objective), current, target),
+            }
+        }
+    }
+}
+
+fn describe_map(game: &Game, view: MapView){
+    let layers: Vec<u8> = match view {
+        MapView::Current => vec![game.player_position.2],
+        MapView::Layer(layer) if (layer as usize) < game.player_map.len() => vec![layer],
+        MapView::Layer(layer) => {
+            println!("O nível {} não existe.", layer);
+            return;
+        }
+        MapView::All => (0..game.player_map.len() as u8).collect(),
+    };
+    for layer in layers{
+        let tiles = &game.player_map[layer as usize];
+        let total = tiles.iter().flatten().count();
+        let explored = tiles.iter().flatten().filter(|tile| !tile.is_empty()).count();
+        println!("Nível {}, {}: {}% explorado.", layer, layer_rules(game, layer).name, explored * 100 / total.max(1));
+        let mut items = Vec::new();
+        for (y, row) in tiles.iter().enumerate(){
+            for (x, tile) in row.iter().enumerate(){
+                if let Some(item) = Item::from_tile(tile) {
+                    items.push(format!("{} em x {}, y {}", item.name(), x, y));
+                }
+            }
+        }
+        if items.is_empty() {
+            println!("Nenhum item conhecido.");
+        }else{
+            println!("Itens conhecidos: {}.", items.join("; "));
+        }
+        for waypoint in game.waypoints.iter().filter(|waypoint| waypoint.position.2 == layer){
+            println!("Marcador {} em x {}, y {}.", waypoint.label, waypoint.position.0, waypoint.position.1);
+        }
+    }
+}
+
+fn game_hud(game: &Game, theme: Theme){
+    let (player_x, player_y, player_z) = game.player_position;
+    println!("\nProfundidade:{}", layer_rules(game, player_z).name);
+    let (view_x, view_y) = scan_window(game);
+    let (view_x, view_y) = (view_x as i8, view_y as i8);
+    let markers = map_markers(game);
+    println!("/{}\\", "-".repeat(view_x as usize * 2 + 1));
+    for y in -view_y..=view_y{
+        print!("|");
+        for x in -view_x..=view_x{
+            let cur_tile_x = player_x as i8 + x;
+            let cur_tile_y = player_y as i8 + y;
+            let (glyph, tile) = if cur_tile_x < 0 || cur_tile_y < 0 || cur_tile_x > 49 || cur_tile_y > 49 {
+                ('#', Tile::BorderRock)
+            }else if game.torpedo_trail.contains(&(cur_tile_x as u8, cur_tile_y as u8)){
+                match game.player_direction {
+                    Direction::East | Direction::West => ('-', Tile::Trail),
+                    _ => ('|', Tile::Trail),
+                }
+            }else if let Some((_, marker)) = markers.iter().find(|(position, _)| *position == (cur_tile_x as u8, cur_tile_y as u8, player_z) && *position != game.player_position){
+                (*marker, Tile::Marker)
+            }else{
+                tile_glyph(game, cur_tile_x as u8, cur_tile_y as u8, player_z)
+            };
+            print!("{}", theme.paint(tile, player_z, &glyph.to_string()));
+        }
+        println!("|");
+    }
+    println!("\\{}/", "-".repeat(view_x as usize * 2 + 1));
+
+    print_position(game);
+    println!("Oxigênio:{} | Casco:{}% | Créditos:{}", game.oxygen, game.hull, game.credits);
+    if let Some(limit) = game.time_limit_ms {
+        let remaining = limit.saturating_sub(game.elapsed_ms) / 1000;
+        println!("Tempo restante: {:02}:{:02}", remaining / 60, remaining % 60);
+    }
+    println!("Torpedos: Padrão {} | Área {} | Profundidade {} (carregado: {})",
+        game.torpedoes.standard, game.torpedoes.wide, game.torpedoes.depth, game.torpedoes.loaded.name());
+    let sonar_status = |turns: u8| if turns == 0 { "pronto".to_string() } else { format!("{} turno(s)", turns) };
+    println!("Sonar: Longo {} | Vertical {}",
+        sonar_status(game.sonar_cooldowns.long), sonar_status(game.sonar_cooldowns.vertical));
+    print_objectives(game);
+}
+
+fn move_sub(game: &mut Game,dir: Direction){
+    let (cur_x, cur_y, cur_z) = game.player_position;
+    let (mut next_x, mut next_y, mut next_z) = (cur_x, cur_y, cur_z);
+
+    let _ = print_movement(&dir);
+
+    match dir {
         Direction::North => {
             game.player_direction = dir;
             if next_y >= 1{
@@ -389,138 +2301,1172 @@ fn move_sub(game: &mut Game,dir: Direction){
                 return;
             }
         },
-        Direction::South => {
-            next_y += 1;
-            game.player_direction = dir;
-    },
-        Direction::East => {
-            next_x += 1;
-            game.player_direction = dir;
-    },
-        Direction::West => {
-            game.player_direction = dir;
-            if next_x >= 1{
-                next_x -= 1;
+        Direction::South => {
+            next_y += 1;
+            game.player_direction = dir;
+    },
+        Direction::East => {
+            next_x += 1;
+            game.player_direction = dir;
+    },
+        Direction::West => {
+            game.player_direction = dir;
+            if next_x >= 1{
+                next_x -= 1;
+            }else{
+                println!("Área fora dos parâmetros dá missão! retornando...");
+                return;
+            }
+    },
+        Direction::Up => {
+            if next_z >=1{
+                next_z -= 1;
+            }else{
+                println!("Altitude máxima já alcançada! Impossível ascender mais...");
+                return;
+            }
+        },
+        Direction::Down => {
+            if next_z < 2 && game.hull < hull_required(game, next_z + 1){
+                println!("O casco está danificado demais para suportar a pressão do nível abaixo! Repare-o na superfície...");
+                return;
+            }
+            next_z += 1
+        },
+    }
+
+    if next_x > 49 || next_y > 49{
+        println!("Área fora dos parâmetros dá missão! retornando...");
+    }else if next_z > 2{
+        println!("Profundidade máxima já alcançada! Impossível descer mais...");
+    } else if let Some(index) = creature_at(game, (next_x, next_y, next_z)){
+        println!("O submarino esbarra em uma {}!", game.creatures[index].kind.name());
+        creature_attack(game, index);
+    } else if game.real_map[next_z as usize][next_y as usize][next_x as usize] == "rock" || game.real_map[next_z as usize][next_y as usize][next_x as usize] == "borderRock"{
+        println!("O submarino se bate em uma rocha! Ouch!");
+        game.stats.collisions += 1;
+        damage_hull(game, game.rules.collision_damage);
+    } else if game.real_map[next_z as usize][next_y as usize][next_x as usize] == "treasure"{
+        println!("O submarino se bate contra algo precioso! Tesouro detectado nas redondezas!");
+    } else {
+        relocate_sub(game, (next_x, next_y, next_z));
+        *game.stats.moves.count(dir) += 1;
+    }
+}
+
+fn relocate_sub(game: &mut Game, next: (u8, u8, u8)){
+    let (cur_x, cur_y, cur_z) = game.player_position;
+    let (next_x, next_y, next_z) = next;
+
+    if let Some(item) = Item::from_tile(&game.real_map[next_z as usize][next_y as usize][next_x as usize]){
+        collect_item(game, item);
+    }
+    game.real_map[cur_z as usize][cur_y as usize][cur_x as usize] = "n/a".to_string();
+    game.real_map[next_z as usize][next_y as usize][next_x as usize] = "player".to_string();
+    game.player_map[cur_z as usize][cur_y as usize][cur_x as usize] = "n/a".to_string();
+    game.player_map[next_z as usize][next_y as usize][next_x as usize] = "player".to_string();
+    if next_z != cur_z {
+        game.turns_at_depth = 0;
+        if !game.stats.layers_visited.contains(&next_z) {
+            game.stats.layers_visited.push(next_z);
+        }
+    }
+    game.player_position = next;
+    game.stats.distance += 1;
+}
+
+fn zone_at(game: &Game, position: (u8, u8, u8)) -> Option<&Zone> {
+    game.zones.iter().find(|z| z.position == position)
+}
+
+fn apply_depth_hazards(game: &mut Game){
+    let rules = layer_rules(game, game.player_position.2);
+    game.turns_at_depth = game.turns_at_depth.saturating_add(1);
+    if rules.stress_turns > 0 && game.turns_at_depth > rules.stress_turns {
+        println!("O casco range sob a pressão das profundezas!");
+        damage_hull(game, rules.stress_damage);
+    }
+
+    let position = game.player_position;
+    if let Some(zone) = game.zones.iter_mut().find(|z| z.position == position && z.kind == ZoneKind::Vent) {
+        zone.revealed = true;
+        println!("Uma fonte hidrotermal superaquece o casco!");
+        damage_hull(game, 10);
+    }
+}
+
+fn reveal_zones(game: &mut Game, layer: u8, range_x: u8, range_y: u8){
+    let (player_x, player_y, _) = game.player_position;
+    for zone in game.zones.iter_mut(){
+        let (x, y, z) = zone.position;
+        if zone.kind == ZoneKind::Vent && z == layer && x.abs_diff(player_x) <= range_x && y.abs_diff(player_y) <= range_y {
+            zone.revealed = true;
+        }
+    }
+}
+
+fn current_at(game: &Game, position: (u8, u8, u8)) -> Option<&Current> {
+    game.currents.iter().find(|c| c.position == position)
+}
+
+fn drift_sub(game: &mut Game){
+    let Some(current) = current_at(game, game.player_position) else {
+        return;
+    };
+    let (dx, dy) = direction_offset(&current.direction);
+    let strength = current.strength;
+
+    println!("A correnteza arrasta o submarino!");
+    for _ in 0..strength{
+        let (cur_x, cur_y, cur_z) = game.player_position;
+        let next_x = cur_x as i8 + dx;
+        let next_y = cur_y as i8 + dy;
+        if !(0..50).contains(&next_x) || !(0..50).contains(&next_y) {
+            return;
+        }
+        let next = (next_x as u8, next_y as u8, cur_z);
+        let tile = &game.real_map[cur_z as usize][next_y as usize][next_x as usize];
+
+        if let Some(index) = creature_at(game, next) {
+            println!("A correnteza joga o submarino contra uma {}!", game.creatures[index].kind.name());
+            creature_attack(game, index);
+            return;
+        }else if tile == "rock" || tile == "borderRock" {
+            println!("A correnteza joga o submarino contra uma rocha!");
+            game.stats.collisions += 1;
+            damage_hull(game, game.rules.collision_damage);
+            return;
+        }else if tile == "treasure" {
+            println!("A correnteza joga o submarino contra algo precioso! Tesouro detectado nas redondezas!");
+            return;
+        }
+        relocate_sub(game, next);
+    }
+}
+
+fn reveal_currents(game: &mut Game, layer: u8, range_x: u8, range_y: u8){
+    let (player_x, player_y, _) = game.player_position;
+    for current in game.currents.iter_mut(){
+        let (x, y, z) = current.position;
+        if z == layer && x.abs_diff(player_x) <= range_x && y.abs_diff(player_y) <= range_y {
+            current.revealed = true;
+        }
+    }
+}
+
+fn direction_offset(dir: &Direction) -> (i8, i8){
+    match dir {
+        Direction::North | Direction::Up | Direction::Down => (0, -1),
+        Direction::South => (0, 1),
+        Direction::East => (1, 0),
+        Direction::West => (-1, 0),
+    }
+}
+
+fn shoot_missile(game: &mut Game, kind: Option<Torpedo>) -> bool {
+    let kind = kind.unwrap_or(game.torpedoes.loaded);
+    let (cur_x, cur_y, cur_z) = game.player_position;
+    let (dx, dy) = direction_offset(&game.player_direction);
+
+    if *game.torpedoes.ammo(kind) == 0 {
+        println!("\nSem munição! Nenhum(a) {} restante a bordo...", kind.name());
+        return false;
+    }
+    if kind == Torpedo::Depth && cur_z >= 2 {
+        println!("\nNão há nada abaixo do submarino para a carga de profundidade atingir!");
+        return false;
+    }
+    *game.torpedoes.ammo(kind) -= 1;
+    game.stats.torpedoes_fired += 1;
+
+    let target_z = match kind {
+        Torpedo::Depth => cur_z + 1,
+        _ => cur_z,
+    } as usize;
+
+    println!("\nO submarino dispara um(a) {} a frente!", kind.name());
+
+    for it in 1..=torpedo_range(game) as i8{
+        let x_loc = cur_x as i8 + dx * it;
+        let y_loc = cur_y as i8 + dy * it;
+        if !(0..50).contains(&x_loc) || !(0..50).contains(&y_loc) {
+            break;
+        }
+        let (x_loc, y_loc) = (x_loc as usize, y_loc as usize);
+        game.torpedo_trail.push((x_loc as u8, y_loc as u8));
+
+        if let Some(index) = creature_at(game, (x_loc as u8, y_loc as u8, target_z as u8)) {
+            torpedo_hits_creature(game, index);
+            return true;
+        }
+
+        if kind != Torpedo::Depth && game.real_map[cur_z as usize][y_loc][x_loc] == "borderRock" {
+            println!("O torpedo se bate em uma rocha robusta! Nenhum dano parece ter ocorrido...");
+            if it == 1 {
+                torpedo_backblast(game, kind);
+            }
+            return true;
+        }
+
+        match game.real_map[target_z][y_loc][x_loc].as_str() {
+            "rock" => {
+                if it == 1 && kind != Torpedo::Depth {
+                    torpedo_backblast(game, kind);
+                }
+                if kind == Torpedo::Wide {
+                    let destroyed = blast_area(game, target_z, x_loc, y_loc);
+                    game.stats.rocks_destroyed += destroyed;
+                    println!("O torpedo explode em uma grande onda de choque! {} rocha(s) destruída(s)!", destroyed);
+                }else{
+                    println!("O torpedo atinge uma rocha e a destrói!");
+                    game.stats.rocks_destroyed += 1;
+                    game.real_map[target_z][y_loc][x_loc] = "n/a".to_string();
+                }
+                return true;
+            },
+            "borderRock" if kind == Torpedo::Depth => {
+                println!("A carga de profundidade atinge uma rocha robusta abaixo! Nenhum dano parece ter ocorrido...");
+                return true;
+            },
+            _ => continue,
+        }
+    }
+    println!("Você não ouve o som do torpedo, deve ter viajado muito longe...");
+    true
+}
+
+fn torpedo_backblast(game: &mut Game, kind: Torpedo){
+    println!("A explosão acontece perto demais do submarino!");
+    match kind {
+        Torpedo::Wide => damage_hull(game, 15),
+        _ => damage_hull(game, 5),
+    }
+}
+
+fn damage_hull(game: &mut Game, amount: u8){
+    game.hull = game.hull.saturating_sub(amount);
+    println!("O casco sofre {} de dano! Integridade: {}%", amount, game.hull);
+}
+
+fn repair_hull(game: &mut Game) -> bool {
+    if game.player_position.2 != 0 {
+        println!("Reparos só podem ser feitos nas águas rasas, próximo a superfície!");
+        return false;
+    }
+    if game.hull >= full_hull() {
+        println!("O casco já está em perfeito estado!");
+        return false;
+    }
+    game.hull = game.hull.saturating_add(25).min(full_hull());
+    println!("A tripulação faz reparos no casco. Integridade: {}%", game.hull);
+    true
+}
+
+fn blast_area(game: &mut Game, z: usize, x: usize, y: usize) -> u32 {
+    let mut destroyed = 0;
+    for blast_y in y.saturating_sub(1)..=(y + 1).min(49){
+        for blast_x in x.saturating_sub(1)..=(x + 1).min(49){
+            if game.real_map[z][blast_y][blast_x] == "rock" {
+                game.real_map[z][blast_y][blast_x] = "n/a".to_string();
+                destroyed += 1;
+            }
+        }
+    }
+    destroyed
+}
+
+fn load_torpedo(game: &mut Game, kind: Torpedo){
+    game.torpedoes.loaded = kind;
+    println!("Tubo de lançamento carregado com: {} ({} restante(s))", kind.name(), game.torpedoes.ammo(kind));
+}
+
+fn collect_item(game: &mut Game, item: Item){
+    game.inventory.push(item);
+    game.credits += item.value();
+    match item.value() {
+        0 => println!("O submarino recolhe: {}!", item.name()),
+        value => println!("O submarino recolhe: {}! (valor: {}, convertido em créditos)", item.name(), value),
+    }
+}
+
+fn upgrade_price(game: &Game, upgrade: Upgrade) -> u32 {
+    upgrade.base_price() * (game.upgrades.level(upgrade) as u32 + 1)
+}
+
+fn print_shop(game: &Game){
+    if game.player_position.2 != 0 {
+        println!("A loja só está disponível nas águas rasas, próximo a superfície!");
+        return;
+    }
+    println!("\nLoja da superfície - Créditos: {}", game.credits);
+    for upgrade in Upgrade::all(){
+        let level = game.upgrades.level(upgrade);
+        if level >= 3 {
+            println!("{} - nível máximo", upgrade.name());
+        }else{
+            println!("{} - nível {}/3 - {} créditos", upgrade.name(), level, upgrade_price(game, upgrade));
+        }
+    }
+    println!("Use 'Buy Tank', 'Buy Torpedo', 'Buy Sonar' ou 'Buy Hull' para comprar");
+}
+
+fn buy_upgrade(game: &mut Game, upgrade: Upgrade) -> bool {
+    if game.player_position.2 != 0 {
+        println!("A loja só está disponível nas águas rasas, próximo a superfície!");
+        return false;
+    }
+    if game.upgrades.level(upgrade) >= 3 {
+        println!("Esta melhoria já está no nível máximo!");
+        return false;
+    }
+    let price = upgrade_price(game, upgrade);
+    if game.credits < price {
+        println!("Créditos insuficientes! ({}/{})", game.credits, price);
+        return false;
+    }
+    game.credits -= price;
+    *game.upgrades.level_mut(upgrade) += 1;
+    if upgrade == Upgrade::OxygenTank {
+        game.oxygen = game.oxygen.saturating_add(40);
+    }
+    println!("Melhoria instalada: {}! Créditos restantes: {}", upgrade.name(), game.credits);
+    true
+}
+
+fn collected_value(game: &Game) -> u32 {
+    game.inventory.iter().map(|item| item.value()).sum()
+}
+
+fn print_inventory(game: &Game){
+    println!("\nInventário:");
+    if game.inventory.is_empty() {
+        println!("Vazio");
+    }
+    for item in [Item::OxygenTank, Item::TorpedoCrate, Item::RepairKit, Item::Pearl, Item::GoldCoins, Item::Artifact, Item::Vault]{
+        let count = game.inventory.iter().filter(|&&i| i == item).count();
+        if count > 0 {
+            println!("{}x {}", count, item.name());
+        }
+    }
+    println!("Valor coletado: {}", collected_value(game));
+}
+
+fn use_item(game: &mut Game, item: Item){
+    let Some(index) = game.inventory.iter().position(|&i| i == item) else {
+        println!("Nenhum(a) {} no inventário!", item.name());
+        return;
+    };
+    match item {
+        Item::OxygenTank => {
+            game.oxygen = game.oxygen.saturating_add(40);
+            println!("O tanque é conectado ao sistema. +40 de oxigênio!");
+        },
+        Item::TorpedoCrate => {
+            game.torpedoes.standard = game.torpedoes.standard.saturating_add(3);
+            game.torpedoes.wide = game.torpedoes.wide.saturating_add(1);
+            game.torpedoes.depth = game.torpedoes.depth.saturating_add(1);
+            println!("A caixa de torpedos é aberta! +3 padrão, +1 de área, +1 de profundidade");
+        },
+        Item::RepairKit => {
+            game.hull = game.hull.saturating_add(30).min(full_hull());
+            println!("A tripulação usa o kit de reparos. Integridade: {}%", game.hull);
+        },
+        _ => {
+            println!("Isso não pode ser usado!");
+            return;
+        },
+    }
+    game.inventory.remove(index);
+}
+
+
+fn spawn_creatures(real_map: &mut [Vec<Vec<String>>]) -> Vec<Creature> {
+    let mut creatures = Vec::new();
+    for (z, layer) in real_map.iter_mut().enumerate(){
+        for (y, row) in layer.iter_mut().enumerate(){
+            for (x, tile) in row.iter_mut().enumerate(){
+                let kind = match tile.as_str() {
+                    "eel" => CreatureKind::Eel,
+                    "squid" => CreatureKind::Squid,
+                    _ => continue,
+                };
+                *tile = "n/a".to_string();
+                creatures.push(Creature{
+                    kind,
+                    position: (x as u8, y as u8, z as u8),
+                    heading: match kind {
+                        CreatureKind::Eel => Direction::East,
+                        CreatureKind::Squid => Direction::North,
+                    },
+                    health: match kind {
+                        CreatureKind::Eel => 1,
+                        CreatureKind::Squid => 2,
+                    },
+                    fleeing: 0,
+                    spotted: false,
+                });
+            }
+        }
+    }
+    creatures
+}
+
+fn creature_at(game: &Game, position: (u8, u8, u8)) -> Option<usize> {
+    game.creatures.iter().position(|c| c.position == position)
+}
+
+fn in_sonar_window(game: &Game, position: (u8, u8, u8)) -> bool {
+    let (player_x, player_y, player_z) = game.player_position;
+    let (x, y, z) = position;
+    let (range_x, range_y) = scan_window(game);
+    z == player_z && x.abs_diff(player_x) <= range_x && y.abs_diff(player_y) <= range_y
+}
+
+fn spot_creatures(game: &mut Game, layer: u8, range_x: u8, range_y: u8){
+    let (player_x, player_y, _) = game.player_position;
+    for creature in game.creatures.iter_mut(){
+        let (x, y, z) = creature.position;
+        if z == layer && x.abs_diff(player_x) <= range_x && y.abs_diff(player_y) <= range_y {
+            creature.spotted = true;
+        }
+    }
+}
+
+fn is_open_water(game: &Game, x: i16, y: i16, z: u8) -> bool {
+    (0..50).contains(&x) && (0..50).contains(&y)
+        && game.real_map[z as usize][y as usize][x as usize] == "n/a"
+        && creature_at(game, (x as u8, y as u8, z)).is_none()
+}
+
+fn creatures_turn(game: &mut Game){
+    for index in 0..game.creatures.len(){
+        let (x, y, z) = game.creatures[index].position;
+        let (player_x, player_y, player_z) = game.player_position;
+        let (dx, dy) = (player_x as i16 - x as i16, player_y as i16 - y as i16);
+
+        if z == player_z && dx.abs() + dy.abs() == 1 && game.creatures[index].fleeing == 0 {
+            creature_attack(game, index);
+            continue;
+        }
+
+        let mut steps = if game.creatures[index].fleeing > 0 {
+            game.creatures[index].fleeing -= 1;
+            if dx.abs() >= dy.abs() {
+                vec![(-dx.signum(), 0), (0, -dy.signum()), (0, dy.signum())]
             }else{
-                println!("Área fora dos parâmetros dá missão! retornando...");
-                return;
+                vec![(0, -dy.signum()), (-dx.signum(), 0), (dx.signum(), 0)]
+            }
+        }else if in_sonar_window(game, (x, y, z)) {
+            if dx.abs() >= dy.abs() {
+                vec![(dx.signum(), 0), (0, dy.signum())]
+            }else{
+                vec![(0, dy.signum()), (dx.signum(), 0)]
+            }
+        }else{
+            let (head_x, head_y) = direction_offset(&game.creatures[index].heading);
+            vec![(head_x as i16, head_y as i16), (-head_x as i16, -head_y as i16)]
+        };
+        steps.retain(|&step| step != (0, 0));
+
+        for (step_x, step_y) in steps{
+            if is_open_water(game, x as i16 + step_x, y as i16 + step_y, z) {
+                let creature = &mut game.creatures[index];
+                creature.position = ((x as i16 + step_x) as u8, (y as i16 + step_y) as u8, z);
+                creature.heading = match (step_x, step_y) {
+                    (0, -1) => Direction::North,
+                    (0, 1) => Direction::South,
+                    (1, 0) => Direction::East,
+                    _ => Direction::West,
+                };
+                break;
+            }
+        }
+
+        let position = game.creatures[index].position;
+        if !in_sonar_window(game, position) {
+            game.creatures[index].spotted = false;
+        }
+    }
+}
+
+fn creature_attack(game: &mut Game, index: usize){
+    match game.creatures[index].kind {
+        CreatureKind::Eel => {
+            println!("Uma enguia elétrica dá um choque no submarino! Parte do oxigênio é perdida...");
+            game.oxygen = game.oxygen.saturating_sub(8);
+        },
+        CreatureKind::Squid => {
+            println!("Uma lula gigante agarra o submarino com seus tentáculos!");
+            damage_hull(game, 15);
+        },
+    }
+    game.creatures[index].spotted = true;
+    game.creatures[index].fleeing = 3;
+}
+
+fn torpedo_hits_creature(game: &mut Game, index: usize){
+    let name = game.creatures[index].kind.name();
+    let creature = &mut game.creatures[index];
+    creature.health = creature.health.saturating_sub(1);
+    if creature.health == 0 {
+        println!("O torpedo atinge uma {}! A criatura foge para longe e não é mais vista...", name);
+        game.creatures.remove(index);
+    }else{
+        println!("O torpedo atinge uma {}! Ferida, ela recua...", name);
+        creature.fleeing = 5;
+        creature.spotted = true;
+    }
+}
+
+fn scare_creatures(game: &mut Game){
+    let player_z = game.player_position.2;
+    for creature in game.creatures.iter_mut(){
+        let (x, y, z) = creature.position;
+        if z == player_z && game.torpedo_trail.iter().any(|&(trail_x, trail_y)| x.abs_diff(trail_x) <= 3 && y.abs_diff(trail_y) <= 3) {
+            creature.fleeing = creature.fleeing.max(4);
+        }
+    }
+}
+
+fn run_scan(game: &mut Game, mode: SonarMode) -> bool {
+    let remaining = *game.sonar_cooldowns.turns(mode);
+    if remaining > 0 {
+        println!("\nO sonar de {} ainda está recarregando! ({} turno(s) restante(s))", mode.name(), remaining);
+        return false;
+    }
+    *game.sonar_cooldowns.turns(mode) = mode.cooldown();
+    game.oxygen = game.oxygen.saturating_sub(mode.oxygen_cost().into());
+    game.stats.scans += 1;
+
+    match mode {
+        SonarMode::Short => short_scan(game),
+        SonarMode::Long => long_scan(game),
+        SonarMode::Vertical => vertical_scan(game),
+    }
+    true
+}
+
+fn reveal_tile(game: &mut Game, x: i8, y: i8, z: usize) -> Option<&str> {
+    if x < 0 || y < 0 || x > 49 || y > 49 {
+        return None;
+    }
+    let position = (x as u8, y as u8, z as u8);
+    if let Some(zone) = game.zones.iter_mut().find(|zone| zone.position == position && zone.kind == ZoneKind::Dark) {
+        zone.revealed = true;
+        return None;
+    }
+    let (x, y) = (x as usize, y as usize);
+    game.player_map[z][y][x] = game.real_map[z][y][x].clone();
+    Some(game.player_map[z][y][x].as_str())
+}
+
+fn short_scan(game: &mut Game){
+    let (player_x, player_y, player_z) = game.player_position;
+    let (range_x, range_y) = scan_window(game);
+    let (range_x, range_y) = (sonar_reach(game, range_x), sonar_reach(game, range_y));
+    let mut blind = 0;
+    println!("\nEscaneando arredores...");
+    for y in -(range_y as i8)..=range_y as i8{
+        for x in -(range_x as i8)..=range_x as i8{
+            let (tile_x, tile_y) = (player_x as i8 + x, player_y as i8 + y);
+            if reveal_tile(game, tile_x, tile_y, player_z as usize).is_none() && (0..50).contains(&tile_x) && (0..50).contains(&tile_y) {
+                blind += 1;
+            }
+        }
+    }
+    spot_creatures(game, player_z, range_x, range_y);
+    reveal_currents(game, player_z, range_x, range_y);
+    reveal_zones(game, player_z, range_x, range_y);
+    if blind > 0 {
+        println!("O sonar não consegue penetrar a escuridão de {} área(s) próximas...", blind);
+    }
+}
+
+fn long_scan(game: &mut Game){
+    let (player_x, player_y, player_z) = game.player_position;
+    let (dx, dy) = direction_offset(&game.player_direction);
+    let mut detected = 0;
+    println!("\nO sonar emite um pulso de longo alcance a frente...");
+    for it in 1..=sonar_reach(game, 25) as i8{
+        for side in -1i8..2{
+            let x = player_x as i8 + dx * it + dy * side;
+            let y = player_y as i8 + dy * it + dx * side;
+            if !(0..50).contains(&x) || !(0..50).contains(&y) {
+                continue;
+            }
+            let tile = &game.real_map[player_z as usize][y as usize][x as usize];
+            if tile == "rock" || tile == "borderRock" {
+                reveal_tile(game, x, y, player_z as usize);
+                detected += 1;
+            }
+        }
+    }
+    println!("O eco retorna: {} obstáculo(s) sólido(s) detectado(s)", detected);
+}
+
+fn vertical_scan(game: &mut Game){
+    let (player_x, player_y, player_z) = game.player_position;
+    println!("\nO sonar emite um pulso vertical...");
+    for (layer, label) in [(player_z as i8 - 1, "acima"), (player_z as i8 + 1, "abaixo")] {
+        if !(0..3).contains(&layer) {
+            println!("Nenhum nível {} do submarino", label);
+            continue;
+        }
+        let reach = sonar_reach(game, 3);
+        let (mut solid, mut open) = (0, 0);
+        for y in -(reach as i8)..=reach as i8{
+            for x in -(reach as i8)..=reach as i8{
+                match reveal_tile(game, player_x as i8 + x, player_y as i8 + y, layer as usize) {
+                    Some("rock") | Some("borderRock") => solid += 1,
+                    Some(_) => open += 1,
+                    None => {},
+                }
+            }
+        }
+        spot_creatures(game, layer as u8, reach, reach);
+        reveal_currents(game, layer as u8, reach, reach);
+        reveal_zones(game, layer as u8, reach, reach);
+        let creatures = game.creatures.iter()
+            .filter(|c| c.position.2 == layer as u8 && c.position.0.abs_diff(player_x) <= reach && c.position.1.abs_diff(player_y) <= reach)
+            .count();
+        println!("Nível {}: {} obstáculo(s), {} área(s) livre(s) e {} criatura(s) detectados", label, solid, open, creatures);
+    }
+}
+
+fn capture_item(game: &mut Game){
+    let (player_x, player_y, player_z) = game.player_position;
+    let (dx, dy) = direction_offset(&game.player_direction);
+    println!("O submarino tenta capturar algo a sua frente...");
+
+    let target_x = player_x as i8 + dx;
+    let target_y = player_y as i8 + dy;
+    if (0..50).contains(&target_x) && (0..50).contains(&target_y) {
+        let (target_x, target_y, z) = (target_x as usize, target_y as usize, player_z as usize);
+        if let Some(item) = Item::from_tile(&game.real_map[z][target_y][target_x]) {
+            game.real_map[z][target_y][target_x] = "n/a".to_string();
+            game.player_map[z][target_y][target_x] = "n/a".to_string();
+            collect_item(game, item);
+            return;
+        }
+    }
+
+    println!("Nada interessante encontrado...");
+}
+
+fn objective_description(game: &Game, objective: &Objective) -> String {
+    match objective {
+        Objective::Treasure{ value } => format!("Reúna {} em tesouros", value),
+        Objective::Reach{ x, y, z } => format!("Alcance as coordenadas ({}, {}, {})", x, y, z),
+        Objective::Scan{ layer, percent } => format!("Escaneie {}% do nível {}", percent, layer_rules(game, *layer).name),
+        Objective::Collect{ item, count } => format!("Colete {}x {}", count, item.name()),
+        Objective::Destroy{ .. } => "Destrua a parede de rochas".to_string(),
+        Objective::Surface{ item } => format!("Retorne às águas rasas com: {}", item.name()),
+    }
+}
+
+fn objective_progress(game: &Game, objective: &Objective) -> (u32, u32) {
+    match objective {
+        Objective::Treasure{ value } => (collected_value(game).min(*value), *value),
+        Objective::Reach{ x, y, z } => ((game.player_position == (*x, *y, *z)) as u32, 1),
+        Objective::Scan{ layer, percent } => {
+            let scanned = game.player_map.get(*layer as usize)
+                .map(|layer| layer.iter().flatten().filter(|tile| !tile.is_empty()).count())
+                .unwrap_or(0);
+            ((scanned as u32 * 100 / 2500).min(*percent as u32), *percent as u32)
+        },
+        Objective::Collect{ item, count } => {
+            let collected = game.inventory.iter().filter(|&&i| i == *item).count() as u32;
+            (collected.min(*count as u32), *count as u32)
+        },
+        Objective::Destroy{ cells } => {
+            let destroyed = cells.iter()
+                .filter(|&&(x, y, z)| game.real_map[z as usize][y as usize][x as usize] != "rock")
+                .count();
+            (destroyed as u32, cells.len() as u32)
+        },
+        Objective::Surface{ item } => ((game.player_position.2 == 0 && game.inventory.contains(item)) as u32, 1),
+    }
+}
+
+fn update_objectives(game: &mut Game) -> bool {
+    game.objectives_done.resize(game.mission.objectives.len(), false);
+    for (index, objective) in game.mission.objectives.iter().enumerate(){
+        let (current, target) = objective_progress(game, objective);
+        if !game.objectives_done[index] && current >= target {
+            game.objectives_done[index] = true;
+            println!("Objetivo concluído: {}!", objective_description(game, objective));
+        }
+    }
+    game.objectives_done.iter().all(|&done| done)
+}
+
+fn print_objectives(game: &Game){
+    println!("Missão: {}", game.mission.name);
+    for (index, objective) in game.mission.objectives.iter().enumerate(){
+        let done = game.objectives_done.get(index).copied().unwrap_or(false);
+        let (current, target) = objective_progress(game, objective);
+        match (done, target) {
+            (true, _) => println!("[X] {}", objective_description(game, objective)),
+            (false, 1) => println!("[ ] {}", objective_description(game, objective)),
+            (false, _) => println!("[ ] {} ({}/{})", objective_description(game, objective), current, target),
+        }
+    }
+}
+
+fn map_markers(game: &Game) -> Vec<((u8, u8, u8), char)> {
+    let waypoints = game.waypoints.iter().enumerate()
+        .map(|(index, waypoint)| (waypoint.position, char::from_digit(index as u32 + 1, 10).unwrap_or('!')));
+    let objectives = game.mission.objectives.iter().enumerate()
+        .filter(|(index, _)| !game.objectives_done.get(*index).copied().unwrap_or(false))
+        .filter_map(|(_, objective)| match objective {
+            Objective::Reach{ x, y, z } => Some(((*x, *y, *z), 'X')),
+            _ => None,
+        });
+    waypoints.chain(objectives).collect()
+}
+
+fn add_waypoint(game: &mut Game, position: Option<(u8, u8, u8)>, label: String){
+    let position = position.unwrap_or(game.player_position);
+    let (x, y, z) = position;
+    if z as usize >= game.player_map.len() || y as usize >= game.player_map[0].len() || x as usize >= game.player_map[0][0].len() {
+        println!("As coordenadas ({}, {}, {}) estão fora do mapa!", x, y, z);
+        return;
+    }
+    if let Some(waypoint) = game.waypoints.iter_mut().find(|waypoint| waypoint.label == label) {
+        waypoint.position = position;
+        println!("Marcador '{}' movido para ({}, {}, {})", label, x, y, z);
+        return;
+    }
+    if game.waypoints.len() >= MAX_WAYPOINTS {
+        println!("Limite de {} marcadores atingido, remova um com 'unmark <nome>'", MAX_WAYPOINTS);
+        return;
+    }
+    game.waypoints.push(Waypoint{ label: label.clone(), position });
+    println!("Marcador {} '{}' criado em ({}, {}, {})", game.waypoints.len(), label, x, y, z);
+}
+
+fn find_waypoint(game: &Game, label: &str) -> Option<usize> {
+    if let Some(index) = game.waypoints.iter().position(|waypoint| waypoint.label == label) {
+        return Some(index);
+    }
+    let matches: Vec<usize> = game.waypoints.iter().enumerate()
+        .filter(|(_, waypoint)| waypoint.label.starts_with(label))
+        .map(|(index, _)| index)
+        .collect();
+    match matches.as_slice() {
+        [index] => Some(*index),
+        [] => {
+            println!("Marcador '{}' não encontrado, use 'waypoints' para ver os marcadores", label);
+            None
+        }
+        _ => {
+            println!("Marcador '{}' é ambíguo, pode ser: {}", label,
+                matches.iter().map(|index| game.waypoints[*index].label.as_str()).collect::<Vec<_>>().join(", "));
+            None
+        }
+    }
+}
+
+fn remove_waypoint(game: &mut Game, label: &str){
+    if let Some(index) = find_waypoint(game, label) {
+        let waypoint = game.waypoints.remove(index);
+        println!("Marcador '{}' removido", waypoint.label);
+    }
+}
+
+fn relative_bearing(from: (u8, u8, u8), to: (u8, u8, u8)) -> String {
+    let (from_x, from_y, from_z) = from;
+    let (x, y, z) = to;
+    let mut parts = Vec::new();
+    if y != from_y {
+        parts.push(format!("{} ao {}", from_y.abs_diff(y), if y < from_y { "norte" } else { "sul" }));
+    }
+    if x != from_x {
+        parts.push(format!("{} a {}", from_x.abs_diff(x), if x > from_x { "leste" } else { "oeste" }));
+    }
+    if z != from_z {
+        parts.push(format!("{} nível(is) {}", from_z.abs_diff(z), if z > from_z { "abaixo" } else { "acima" }));
+    }
+    parts.join(", ")
+}
+
+fn waypoint_distance(game: &Game, waypoint: &Waypoint) -> String {
+    let (player_x, player_y, player_z) = game.player_position;
+    let (x, y, z) = waypoint.position;
+    let distance = player_x.abs_diff(x) as u16 + player_y.abs_diff(y) as u16 + player_z.abs_diff(z) as u16;
+    if distance == 0 {
+        return "você está aqui".to_string();
+    }
+    format!("{} casas: {}", distance, relative_bearing(game.player_position, waypoint.position))
+}
+
+fn print_waypoints(game: &Game){
+    if game.waypoints.is_empty() {
+        println!("Nenhum marcador definido, use 'mark <nome>' para marcar a posição atual");
+        return;
+    }
+    println!("Marcadores:");
+    for (index, waypoint) in game.waypoints.iter().enumerate(){
+        let (x, y, z) = waypoint.position;
+        println!("{}. {} ({}, {}, {} - {}) - {}", index + 1, waypoint.label, x, y, z, layer_rules(game, z).name, waypoint_distance(game, waypoint));
+    }
+}
+
+fn is_passable(game: &Game, (x, y, z): (u8, u8, u8)) -> bool {
+    !matches!(game.player_map[z as usize][y as usize][x as usize].as_str(), "rock" | "borderRock")
+}
+
+fn step_towards(game: &Game, target: (u8, u8, u8)) -> Option<Direction> {
+    let layers = game.player_map.len() as i16;
+    let height = game.player_map[0].len() as i16;
+    let width = game.player_map[0][0].len() as i16;
+    let index = |(x, y, z): (u8, u8, u8)| (z as usize * height as usize + y as usize) * width as usize + x as usize;
+    let mut first_step: Vec<Option<Option<Direction>>> = vec![None; (layers * height * width) as usize];
+    let mut queue = VecDeque::from([game.player_position]);
+    first_step[index(game.player_position)] = Some(None);
+
+    while let Some(position) = queue.pop_front(){
+        if position == target {
+            return first_step[index(position)].flatten();
+        }
+        let (x, y, z) = (position.0 as i16, position.1 as i16, position.2 as i16);
+        for direction in DIRECTIONS.map(|(_, direction)| direction){
+            let (nx, ny, nz) = match direction {
+                Direction::North => (x, y - 1, z),
+                Direction::South => (x, y + 1, z),
+                Direction::East => (x + 1, y, z),
+                Direction::West => (x - 1, y, z),
+                Direction::Up => (x, y, z - 1),
+                Direction::Down => (x, y, z + 1),
+            };
+            if nx < 0 || ny < 0 || nz < 0 || nx >= width || ny >= height || nz >= layers {
+                continue;
+            }
+            let next = (nx as u8, ny as u8, nz as u8);
+            if first_step[index(next)].is_some() || !is_passable(game, next) {
+                continue;
+            }
+            if nz > z && game.hull < hull_required(game, nz as u8) {
+                continue;
+            }
+            first_step[index(next)] = Some(first_step[index(position)].flatten().or(Some(direction)));
+            queue.push_back(next);
+        }
+    }
+    None
+}
+
+fn go_to_waypoint(game: &mut Game, label: &str) -> bool {
+    let Some(index) = find_waypoint(game, label) else {
+        return false;
+    };
+    let waypoint = game.waypoints[index].clone();
+    if waypoint.position == game.player_position {
+        println!("O submarino já está no marcador '{}'", waypoint.label);
+        return false;
+    }
+    match step_towards(game, waypoint.position) {
+        Some(direction) => {
+            move_sub(game, direction);
+            if game.player_position == waypoint.position {
+                println!("O submarino chegou ao marcador '{}'!", waypoint.label);
+                return false;
+            }
+            true
+        }
+        None => {
+            println!("Nenhuma rota conhecida até o marcador '{}'", waypoint.label);
+            false
+        }
+    }
+}
+
+fn terminal_width() -> usize {
+    terminal_size::terminal_size().map_or(80, |(terminal_size::Width(width), _)| width as usize)
+}
+
+fn map_glyph(game: &Game, theme: Theme, markers: &[((u8, u8, u8), char)], x: u8, y: u8, z: u8) -> String {
+    let (glyph, tile) = match markers.iter().find(|(position, _)| *position == (x, y, z) && *position != game.player_position) {
+        Some((_, marker)) => (*marker, Tile::Marker),
+        None => match tile_glyph(game, x, y, z) {
+            (' ', Tile::Unexplored) => ('.', Tile::Unexplored),
+            glyph => glyph,
+        },
+    };
+    theme.paint(tile, z, &glyph.to_string())
+}
+
+fn print_map(game: &Game, theme: Theme, view: MapView, show_markers: bool){
+    let layer_count = game.player_map.len() as u8;
+    let layers: Vec<u8> = match view {
+        MapView::Current => vec![game.player_position.2],
+        MapView::Layer(layer) if layer < layer_count => vec![layer],
+        MapView::Layer(layer) => {
+            println!("O nível {} não existe, escolha um nível de 0 a {}", layer, layer_count - 1);
+            return;
+        }
+        MapView::All => (0..layer_count).collect(),
+    };
+    let markers = if show_markers { map_markers(game) } else { Vec::new() };
+    let height = game.player_map[0].len();
+    let width = game.player_map[0][0].len();
+
+    let max_columns = terminal_width().saturating_sub(5).max(10);
+    let mut panels = Vec::new();
+    for &layer in &layers{
+        for start in (0..width).step_by(max_columns){
+            panels.push((layer, start..(start + max_columns).min(width)));
+        }
+    }
+    let mut pages: Vec<Vec<(u8, std::ops::Range<usize>)>> = Vec::new();
+    let mut used = 0;
+    for panel in panels{
+        let panel_width = panel.1.len() + 5;
+        match pages.last_mut() {
+            Some(page) if used + 2 + panel_width <= max_columns + 5 => {
+                page.push(panel);
+                used += 2 + panel_width;
+            }
+            _ => {
+                pages.push(vec![panel]);
+                used = panel_width;
+            }
+        }
+    }
+
+    for page in pages{
+        let mut lines = vec![String::new(); height + 5];
+        for (index, (layer, columns)) in page.iter().enumerate(){
+            if index > 0 {
+                lines.iter_mut().for_each(|line| line.push_str("  "));
+            }
+            let title = format!("{} ({}, x {}-{})", layer, layer_rules(game, *layer).name, columns.start, columns.end - 1);
+            lines[0].push_str(&format!("{:<w$}", title, w = columns.len() + 5));
+            lines[1].push_str("    ");
+            lines[2].push_str("    ");
+            for x in columns.clone(){
+                lines[1].push(if x % 10 == 0 { char::from_digit((x / 10 % 10) as u32, 10).unwrap() } else { ' ' });
+                lines[2].push(char::from_digit((x % 10) as u32, 10).unwrap());
             }
-    },
-        Direction::Up => {
-            if next_z >=1{
-                next_z -= 1;
-            }else{
-                println!("Altitude máxima já alcançada! Impossível ascender mais...");
-                return;
+            lines[1].push(' ');
+            lines[2].push(' ');
+            lines[3].push_str(&format!("   /{}\\", "-".repeat(columns.len())));
+            for y in 0..height{
+                lines[y + 4].push_str(&format!("{:>2} |", y));
+                for x in columns.clone(){
+                    lines[y + 4].push_str(&map_glyph(game, theme, &markers, x as u8, y as u8, *layer));
+                }
+                lines[y + 4].push('|');
             }
-        },
-        Direction::Down => next_z += 1,
+            lines[height + 4].push_str(&format!("   \\{}/", "-".repeat(columns.len())));
+        }
+        println!();
+        for line in lines{
+            println!("{}", line.trim_end());
+        }
+    }
+    let layer = layers[0];
+    let key = |tile: Tile, glyphs: &str, description: &str| format!("{} {}", theme.paint(tile, layer, glyphs), description);
+    let mut legend = vec![key(Tile::Player, "^ v < >", "submarino"), key(Tile::Rock, "#", "rocha")];
+    if theme != Theme::Plain {
+        legend.push(key(Tile::BorderRock, "#", "rocha indestrutível"));
     }
+    legend.extend([key(Tile::Unexplored, ".", "inexplorado"), key(Tile::Treasure, "* $ &", "tesouros"),
+        key(Tile::Item, "o + r", "oxigênio, torpedos e reparos")]);
+    println!("Legenda: {}", legend.join(" | "));
+    let mut legend = vec![key(Tile::Creature, "~ @", "enguia e lula"), key(Tile::Vent, "%", "fonte hidrotermal"),
+        key(Tile::Dark, "░", "zona escura"), key(Tile::Current, "↑ ↓ → ←", "correnteza")];
+    if show_markers {
+        legend.extend([key(Tile::Marker, "1-9", "marcadores"), key(Tile::Marker, "X", "objetivo")]);
+    }
+    println!("         {}", legend.join(" | "));
+}
 
-    if next_x > 49 || next_y > 49{
-        println!("Área fora dos parâmetros dá missão! retornando...");
-        return;
-    }else if next_z > 2{
-        println!("Profundidade máxima já alcançada! Impossível descer mais...");
-        return;
-    } else if game.real_map[next_z as usize][next_y as usize][next_x as usize] == "rock" || game.real_map[next_z as usize][next_y as usize][next_x as usize] == "borderRock"{
-        println!("O submarino se bate em uma rocha! Ouch!");
-    } else if game.real_map[next_z as usize][next_y as usize][next_x as usize] == "treasure"{
-        println!("O submarino se bate contra algo precioso! Tesouro detectado nas redondezas!");
-    } else {
-        game.real_map[cur_z as usize][cur_y as usize][cur_x as usize] = "n/a".to_string();
-        game.real_map[next_z as usize][next_y as usize][next_x as usize] = "player".to_string();
-        game.player_map[cur_z as usize][cur_y as usize][cur_x as usize] = "n/a".to_string();
-        game.player_map[next_z as usize][next_y as usize][next_x as usize] = "player".to_string();
-        game.player_position = (next_x as u8, next_y as u8, next_z as u8);
+fn explored_tiles(game: &Game) -> u32 {
+    game.player_map.iter().flatten().flatten().filter(|tile| !tile.is_empty()).count() as u32
+}
 
-    }
+fn compute_score(game: &Game) -> u32 {
+    let base = collected_value(game) as i64
+        + game.oxygen as i64 * 5
+        + game.stats.rocks_destroyed as i64 * 10
+        + explored_tiles(game) as i64
+        - game.stats.turns as i64 * 2;
+    (base.max(0) * game.rules.score_multiplier as i64 / 100) as u32
 }
 
-fn shoot_missile(game: &mut Game){
-    let (cur_x , cur_y, cur_z) = game.player_position;
-    let (mut x_loc, mut y_loc) = (0,0);
+fn load_leaderboard() -> std::io::Result<Vec<LeaderboardEntry>> {
+    if !Path::new("saves/leaderboard.json").exists() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read_to_string("saves/leaderboard.json")?;
+    let entries: Vec<LeaderboardEntry> = serde_json::from_str(&data)?;
+    Ok(entries)
+}
 
-    println!("\nO submarino dispara um torpedo a frente!");
+fn record_score(game: &Game, score: u32) -> std::io::Result<()> {
+    let mut entries = load_leaderboard()?;
+    entries.push(LeaderboardEntry{
+        mode: game.mode,
+        sub_name: game.sub_name.clone(),
+        map_name: game.map_name.clone(),
+        seed: game.seed,
+        difficulty: game.rules.name.clone(),
+        score,
+        turns: game.stats.turns,
+        date: Utc::now(),
+    });
 
-    for it in 1..11{
-        match game.player_direction {
-            Direction::North | Direction::Up | Direction::Down => y_loc = -it  as i8,
-            Direction::South => y_loc = it,
-            Direction::East => x_loc = it,
-            Direction::West => x_loc = -it  as i8,
-        }
+    let mut file = File::create("saves/leaderboard.json")?;
+    let data = serde_json::to_string(&entries)?;
+    file.write_all(data.as_bytes())?;
+    Ok(())
+}
 
-        if game.real_map[cur_z as usize][(cur_y as i8 + y_loc as i8) as usize][(cur_x as i8 + x_loc as i8) as usize] == "n/a".to_string(){
-            continue;
-        }else if game.real_map[cur_z as usize][(cur_y as i8 + y_loc as i8) as usize][(cur_x as i8 + x_loc as i8) as usize] == "borderRock".to_string(){
-            println!("O torpedo se bate em uma rocha robusta! Nenhum dano parece ter ocorrido...");
-            return;
-        }else if game.real_map[cur_z as usize][(cur_y as i8 + y_loc as i8) as usize][(cur_x as i8 + x_loc as i8) as usize] == "rock".to_string(){
-            println!("O torpedo atinge uma rocha e a destrói!");
-            game.real_map[cur_z as usize][(cur_y as i8 + y_loc as i8) as usize][(cur_x as i8 + x_loc as i8) as usize] = "n/a".to_string();
-            return;
-        }
+fn print_stats(game: &Game){
+    let stats = &game.stats;
+    println!("\nEstatísticas do submarino '{}':", game.sub_name);
+    println!("Turnos: {}", stats.turns);
+    println!("Movimentos: Norte {} | Sul {} | Leste {} | Oeste {} | Subidas {} | Descidas {}",
+        stats.moves.north, stats.moves.south, stats.moves.east, stats.moves.west, stats.moves.up, stats.moves.down);
+    println!("Colisões: {}", stats.collisions);
+    println!("Escaneamentos: {}", stats.scans);
+    println!("Torpedos disparados: {}", stats.torpedoes_fired);
+    println!("Rochas destruídas: {}", stats.rocks_destroyed);
+    println!("Casas descobertas: {}", explored_tiles(game));
+    println!("Distância percorrida: {} casas", stats.distance);
+    for (layer, turns) in stats.turns_per_layer.iter().enumerate(){
+        println!("Turnos em {}: {}", layer_rules(game, layer as u8).name, turns);
     }
-    println!("Você não ouve o som do torpedo, deve ter viajado muito longe...");
 }
 
+fn print_leaderboard(){
+    let mut entries = match load_leaderboard() {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("Erro ao carregar o placar: {}", e);
+            return;
+        },
+    };
+    if entries.is_empty() {
+        println!("\nNenhuma partida registrada no placar ainda!");
+        return;
+    }
 
-fn run_scan(game: &mut Game){
-    let (player_x, player_y, player_z) = game.player_position;
-    println!("\nEscaneando arredores...");
-    for y in -6i8..7{
-        for x in -9i8..10{
-            let cur_tile_x = player_x as i8 + x;
-            let cur_tile_y = player_y as i8 + y;
-            if !(cur_tile_x < 0 || cur_tile_y < 0 || cur_tile_x > 49 || cur_tile_y > 49) {
-                game.player_map[player_z as usize][cur_tile_y as usize][cur_tile_x as usize] = game.real_map[player_z as usize][cur_tile_y as usize][cur_tile_x as usize].clone();
+    entries.sort_by(|a, b| (a.mode, &a.map_name, a.seed).cmp(&(b.mode, &b.map_name, b.seed)).then(b.score.cmp(&a.score)));
+    let mut last_table: Option<(GameMode, &str, Option<u64>)> = None;
+    let mut position = 0;
+    for entry in &entries{
+        let table = (entry.mode, entry.map_name.as_str(), entry.seed);
+        if last_table != Some(table) {
+            match entry.seed {
+                Some(seed) => println!("\nPlacar {} - Mapa: {} (semente {})", entry.mode.name(), entry.map_name, seed),
+                None => println!("\nPlacar {} - Mapa: {}", entry.mode.name(), entry.map_name),
             }
+            last_table = Some(table);
+            position = 0;
+        }
+        position += 1;
+        if position <= 10 {
+            println!("{}. {} - {} pontos ({}, {} turnos, {})", position, entry.sub_name, entry.score,
+                entry.difficulty, entry.turns, entry.date.format("%d/%m/%Y"));
         }
     }
 }
 
-fn capture_item(game: &Game) -> bool {
-    let (player_x, player_y, player_z) = game.player_position;
-    println!("O submarino tenta capturar algo a sua frente...");
+fn load_profile() -> std::io::Result<Profile> {
+    if !Path::new("saves/profile.json").exists() {
+        return Ok(Profile::default());
+    }
+    let data = fs::read_to_string("saves/profile.json")?;
+    let profile: Profile = serde_json::from_str(&data)?;
+    Ok(profile)
+}
 
-    match game.player_direction {
-        Direction::North | Direction::Up | Direction::Down => {
-            if game.real_map[player_z as usize][(player_y - 1) as usize][player_x as usize] == "treasure"{
-                return true;
-            }
-        },
-        Direction::South => {
-            if game.real_map[player_z as usize][(player_y + 1) as usize][player_x as usize] == "treasure"{
-                return true;
-            }
-        },
-        Direction::East => {
-            if game.real_map[player_z as usize][player_y as usize][(player_x + 1) as usize] == "treasure"{
-                return true;
-            }
-        },
-        Direction::West => {
-            if game.real_map[player_z as usize][player_y as usize][(player_x - 1) as usize] == "treasure"{
-                return true;
+fn save_profile(profile: &Profile) -> std::io::Result<()> {
+    let mut file = File::create("saves/profile.json")?;
+    let data = serde_json::to_string(profile)?;
+    file.write_all(data.as_bytes())?;
+    Ok(())
+}
+
+fn check_achievements(game: &Game, profile: &mut Profile, accessible: bool){
+    let won = game.outcome == Some(Outcome::Won);
+    let mut unlocked_any = false;
+
+    for achievement in Achievement::all(){
+        if profile.achievements.iter().any(|a| a.achievement == achievement) {
+            continue;
+        }
+        let unlocked = match achievement {
+            Achievement::FirstWin => won,
+            Achievement::Pacifist => won && game.stats.torpedoes_fired == 0,
+            Achievement::DeepBreath => won && game.oxygen > oxygen_capacity(game) / 2,
+            Achievement::Explorer => (0..3).all(|layer| game.stats.layers_visited.contains(&layer)),
+            Achievement::Demolisher => profile.rocks_destroyed >= 50,
+        };
+        if unlocked {
+            if accessible {
+                println!("Conquista desbloqueada: {}. {}.", achievement.name(), achievement.description());
+            }else{
+                println!("*** Conquista desbloqueada: {} - {} ***", achievement.name(), achievement.description());
             }
-        },
+            profile.achievements.push(UnlockedAchievement{ achievement, date: Utc::now() });
+            unlocked_any = true;
+        }
     }
 
-    println!("Nada interessante encontrado...");
-    return false;
+    if unlocked_any {
+        if let Err(e) = save_profile(profile) {
+            println!("Falha ao salvar o perfil: {}", e);
+        }
+    }
+}
+
+fn print_achievements(){
+    let profile = load_profile().unwrap_or_default();
+    println!("\nConquistas: {}/{}", profile.achievements.len(), Achievement::all().len());
+    for achievement in Achievement::all(){
+        match profile.achievements.iter().find(|a| a.achievement == achievement) {
+            Some(unlocked) => println!("[X] {} - {} ({})", achievement.name(), achievement.description(), unlocked.date.format("%d/%m/%Y")),
+            None => println!("[ ] {} - {}", achievement.name(), achievement.description()),
+        }
+    }
 }
 
 fn print_help(){
     println!("Lista de comandos:");
     println!("Move [] - Move o návio na direção específicada\nOpções: North: Move o submarino na direção norte\n------> South: Move o submarino na direção sul\n------> East: Move o submarino na direção leste\n------> West: Move o submarino na direção oeste\n------> Up: Sobe o submarino 1 nível\n------> Down: Desce o submarino 1 nível");
-    println!("Scan - Detecta obstáculos e items ao redor do návio");
-    println!("Shoot - Dispara um torpedo a frente do návio, útil para se livrar de rochas");
+    println!("Scan [] - Detecta obstáculos e items ao redor do návio\nOpções: Short: Revela toda a área ao redor do návio (padrão, sem custo)\n------> Long: Pulso direcional de longo alcance, detecta apenas obstáculos sólidos (3 de oxigênio, recarga de 4 turnos)\n------> Vertical: Espia os níveis acima e abaixo do návio (2 de oxigênio, recarga de 3 turnos)");
+    println!("Shoot [] - Dispara um torpedo a frente do návio, útil para se livrar de rochas\nOpções: Standard: Torpedo padrão, destrói a primeira rocha no caminho\n------> Wide: Torpedo de área, destrói as rochas em uma área 3x3 no impacto\n------> Depth: Carga de profundidade, atinge a primeira rocha do nível abaixo\n------> (vazio): Dispara o torpedo carregado no tubo");
+    println!("Load [] - Carrega o tubo de lançamento com o tipo de torpedo escolhido (Standard, Wide, Depth)");
     println!("Capture - Pega um item a frente do návio, use para obter o tesouro!");
+    println!("Inventory - Lista os itens coletados e o valor total dos tesouros");
+    println!("Use [] - Usa um item do inventário\nOpções: Oxygen: Tanque de oxigênio, +40 de oxigênio\n------> Torpedoes: Caixa de torpedos, reabastece o estoque de torpedos\n------> Repair: Kit de reparos, repara 30% do casco");
+    println!("Repair - Repara o casco do návio em 25%, disponível apenas nas águas rasas");
     println!("Save - Salva o progresso do seu jogo");
+    println!("Stats - Exibe as estatísticas da partida");
+    println!("Shop - Exibe a loja de melhorias, disponível apenas nas águas rasas");
+    println!("Buy [] - Compra uma melhoria na loja\nOpções: Tank: Tanque de oxigênio ampliado\n------> Torpedo: Propulsores de torpedo\n------> Sonar: Sonar aprimorado\n------> Hull: Casco reforçado");
+    println!("Map [] - Exibe o mapa explorado completo, sem gastar turnos\nOpções: (vazio): Nível atual\n------> 0, 1 ou 2: Nível escolhido\n------> All: Todos os níveis lado a lado\n------> Plain: Oculta os marcadores de objetivos");
+    println!("Mark [] - Cria um marcador na posição atual (ex: 'mark base') ou nas coordenadas indicadas (ex: 'mark 10 20 1 tesouro')");
+    println!("Unmark [] - Remove o marcador indicado");
+    println!("Waypoints - Lista os marcadores e a distância até cada um");
+    println!("Where - Exibe a posição, o rumo, a distância percorrida e a bússola do submarino");
+    println!("Go [] - Avança o submarino 1 casa em direção ao marcador indicado (ex: 'go base 10')");
+    println!("Undo - Desfaz o último turno, se a dificuldade permitir");
+    println!("Redo - Refaz o último turno desfeito");
+    println!("Leaderboard - Exibe o placar com as melhores partidas de cada mapa");
     println!("Help - Você já sabe o que isso faz!");
     println!("Quit - Termina a execução do jogo");
-    println!("OBS: Os comandos aqui expostos NÃO são case-sensitive");
+    println!("OBS: Os comandos aqui expostos NÃO são case-sensitive e podem ser abreviados (ex: 'mv e', 'sc long')");
+    println!("Atalhos definidos em assets/keymap.csv: n, e, w, u, d (movimento), s (scan), sh (shoot), l (load), c (capture), i (inventory), h (help), q (quit)");
+    println!("Alias [] [] - Define um atalho para um comando (ex: 'alias fw move north'). Sem comando, remove o atalho; sem nome, lista os atalhos e macros");
+    println!("Theme [] - Escolhe o tema de cores do mapa\nOpções: Classic: Cores padrão, com o fundo tingido pela profundidade\n------> Contrast: Alto contraste\n------> Colorblind: Paleta segura para daltônicos\n------> Plain: Sem cores");
+    println!("Macro [] - Gerencia sequências de comandos\nOpções: Define [nome] [comandos]: Grava uma sequência de comandos separados por ';'\n------> Run [nome]: Executa a sequência gravada\n------> Delete [nome]: Apaga a sequência\n------> List: Lista os atalhos e macros");
+    println!("Use as setas para navegar no histórico de comandos e Tab para completar comandos, opções e nomes de jogos salvos");
+    println!("Ctrl-C ou Ctrl-D encerram a partida, oferecendo salvar o jogo antes de sair");
+    println!("Adicione um número ao fim do comando para repeti-lo (ex: 'move north 5') e separe vários comandos com ';' (ex: 'n; s; sh')");
 }
 
 #[allow(unused_assignments)]
@@ -528,68 +3474,428 @@ fn game_loop(mut game: Game, is_demo : bool){
     println!("\nJogo começado! Digite 'Help' para saber como dirigir o submarino '{}'", &game.sub_name);
     
     let mut demo_steps = 0;
+    let mut profile = load_profile().unwrap_or_default();
+    let mut rocks_destroyed = game.stats.rocks_destroyed;
+    let mut clock = Instant::now();
+    let keymap = load_keymap("assets/keymap.csv").unwrap_or_else(|_| {
+        println!("Não foi possível carregar os atalhos de teclado, apenas os comandos completos estarão disponíveis...");
+        Vec::new()
+    });
+    let mut config = load_user_config().unwrap_or_default();
+    if is_demo {
+        config.aliases.clear();
+        config.macros.clear();
+    }
+    let mut queued_actions: VecDeque<Action> = VecDeque::new();
+    let mut history = UndoHistory::default();
+
+    let mut show_hud = true;
+    let mut last_vitals: Option<(u16, u8)> = None;
 
     loop {
-        game_hud(&game);
+        if show_hud && config.accessible{
+            accessible_hud(&game, last_vitals);
+            last_vitals = Some((game.oxygen, game.hull));
+        }else if show_hud{
+            game_hud(&game, active_theme(&config));
+        }
+        show_hud = true;
+        game.torpedo_trail.clear();
 
-        if game.oxygen <= 0{
+        if game.oxygen == 0{
             println!("Seu oxigênio acaba!");
             println!("O submarino rapidamente ascende para a superfície, e uma equipe de resgate lhe ajuda a sair");
-            println!("O cofre nunca foi encontrado...");
+            println!("A missão '{}' não foi concluída...", game.mission.name);
             println!("FIM DE JOGO");
+            game.outcome = Some(Outcome::Lost);
             break;
         }
 
-        let mut input = "".to_string();
-        if !is_demo{
-            input = get_player_input();
-        }else{
-            thread::sleep(Duration::from_millis(500));
-            let demo_inputs = load_demo_csv().expect("REASON");
-            if demo_steps+1 > demo_inputs.len(){
-                println!("Simulação falha, fechando o jogo...");
-                break;
+        if time_expired(&mut game, &mut clock){
+            break;
+        }
+
+        if game.hull == 0{
+            println!("O casco do submarino cede à pressão!");
+            println!("A tripulação aciona as cápsulas de emergência e é resgatada na superfície");
+            println!("A missão '{}' não foi concluída...", game.mission.name);
+            println!("FIM DE JOGO");
+            game.outcome = Some(Outcome::Lost);
+            break;
+        }
+
+        if queued_actions.is_empty(){
+            let mut input = "".to_string();
+            if !is_demo{
+                match get_command_input(&keymap, &config, &game) {
+                    Some(line) => input = line,
+                    None => {
+                        confirm_quit(&mut game);
+                        break;
+                    }
+                }
+            }else{
+                thread::sleep(Duration::from_millis(500));
+                let demo_inputs = load_demo_csv().expect("REASON");
+                if demo_steps+1 > demo_inputs.len(){
+                    println!("Simulação falha, fechando o jogo...");
+                    break;
+                }
+                input = demo_inputs[demo_steps].clone();
+                println!("{}",input);
+                demo_steps += 1;
+            }
+            if handle_config_command(&input, &mut config, &keymap){
+                continue;
+            }
+            match parse_player_input(&input, &CommandContext{ keymap: &keymap, config: &config }) {
+                Ok(actions) => queued_actions.extend(actions),
+                Err(error) => print_parse_error(&input, &error, config.accessible),
+            }
+            if queued_actions.is_empty(){
+                continue;
+            }
+        }
+        if time_expired(&mut game, &mut clock){
+            break;
+        }
+        let action = queued_actions.pop_front();
+        match &action {
+            Some(Action::Undo) => {
+                undo_turn(&mut game, &mut history);
+                continue;
+            }
+            Some(Action::Redo) => {
+                redo_turn(&mut game, &mut history);
+                continue;
+            }
+            Some(Action::Map(view, show_markers)) => {
+                if config.accessible{
+                    describe_map(&game, *view);
+                }else{
+                    print_map(&game, active_theme(&config), *view, *show_markers);
+                }
+                show_hud = false;
+                continue;
+            }
+            Some(Action::Mark(position, label)) => {
+                add_waypoint(&mut game, *position, label.clone());
+                continue;
             }
-            input = demo_inputs[demo_steps].clone();
-            println!("{}",input);
-            demo_steps += 1;
+            Some(Action::Unmark(label)) => {
+                remove_waypoint(&mut game, label);
+                continue;
+            }
+            Some(Action::Waypoints) => {
+                print_waypoints(&game);
+                show_hud = false;
+                continue;
+            }
+            Some(Action::Where) => {
+                if config.accessible{
+                    println!("{} Distância percorrida: {} casas.", describe_position(&game), game.stats.distance);
+                }else{
+                    print_position(&game);
+                }
+                show_hud = false;
+                continue;
+            }
+            Some(Action::Inventory) => {
+                print_inventory(&game);
+                show_hud = false;
+                continue;
+            }
+            Some(Action::Stats) => {
+                print_stats(&game);
+                show_hud = false;
+                continue;
+            }
+            Some(Action::Leaderboard) => {
+                print_leaderboard();
+                show_hud = false;
+                continue;
+            }
+            Some(Action::Shop) => {
+                print_shop(&game);
+                show_hud = false;
+                continue;
+            }
+            Some(Action::Help) => {
+                print_help();
+                show_hud = false;
+                continue;
+            }
+            Some(Action::Save) if game.rules.ironman => {
+                println!("No modo ironman o jogo é salvo automaticamente a cada turno!");
+                continue;
+            }
+            _ => {}
         }
-        match match_player_input(&input) {
+        let snapshot = (game.rules.allows_undo() && !is_demo).then(|| take_snapshot(&mut game));
+        let hull_before = game.hull;
+        match action {
             Some(Action::Move(dir)) => {
                 move_sub(&mut game, dir);
             }
-            Some(Action::Scan) => {
-                run_scan(&mut game);
+            Some(Action::Scan(mode)) => {
+                if !run_scan(&mut game, mode){
+                    continue;
+                }
+            }
+            Some(Action::Shoot(kind)) =>{
+                if !shoot_missile(&mut game, kind){
+                    continue;
+                }
+                scare_creatures(&mut game);
             }
-            Some(Action::Shoot) =>{
-                shoot_missile(&mut game);
+            Some(Action::Load(kind)) =>{
+                load_torpedo(&mut game, kind);
             }
             Some(Action::Capture) =>{
-                if capture_item(&game){
-                    println!("Sucesso! Você obteve o 'Cofre de Matthew Sheldrake'!");
-                    println!("Após algum tempo, o submarino retorna a superfície...");
-                    println!("Com sua nova fortuna, você vive uma vida próspera e luxuosa!");
-                    println!("FIM DE JOGO");
-                    break;
-                }
+                capture_item(&mut game);
+            }
+            Some(Action::Use(item)) =>{
+                use_item(&mut game, item);
             }
             Some(Action::Quit) =>{
                 println!("Encerrando o jogo...");
                 break;
             }
+            Some(Action::Repair) =>{
+                if !repair_hull(&mut game){
+                    continue;
+                }
+            }
             Some(Action::Save) =>{
                 game.last_save = Utc::now();
                 let _ =update_save_file(&game);
             }
-            Some(Action::Help) =>{
-                print_help();
+            Some(Action::Buy(upgrade)) =>{
+                if !buy_upgrade(&mut game, upgrade){
+                    continue;
+                }
+            }
+            Some(Action::Go(label)) =>{
+                if !go_to_waypoint(&mut game, &label){
+                    queued_actions.retain(|action| !matches!(action, Action::Go(_)));
+                }
+            }
+            Some(Action::Undo) | Some(Action::Redo) | Some(Action::Map(..)) | Some(Action::Mark(..))
+                | Some(Action::Unmark(_)) | Some(Action::Waypoints) | Some(Action::Where) | Some(Action::Inventory)
+                | Some(Action::Stats) | Some(Action::Leaderboard) | Some(Action::Shop) | Some(Action::Help) | None => {}
+        }
+
+        if update_objectives(&mut game){
+            for line in &game.mission.ending{
+                println!("{}", line.replace("{sub_name}", &game.sub_name));
+            }
+            println!("FIM DE JOGO");
+            game.outcome = Some(Outcome::Won);
+            if !is_demo{
+                check_achievements(&game, &mut profile, config.accessible);
+            }
+            let score = compute_score(&game);
+            println!("Pontuação final: {}", score);
+            if !is_demo{
+                if let Err(e) = record_score(&game, score){
+                    println!("Falha ao registrar a pontuação no placar: {}", e);
+                }
+            }
+            if let Some(next) = advance_campaign(&game){
+                print_stats(&game);
+                game = next;
+                rocks_destroyed = 0;
+                last_vitals = None;
+                history = UndoHistory::default();
+                continue;
+            }
+            break;
+        }
+
+        creatures_turn(&mut game);
+        drift_sub(&mut game);
+        apply_depth_hazards(&mut game);
+        game.oxygen = game.oxygen.saturating_sub(layer_rules(&game, game.player_position.2).oxygen_drain.into());
+        game.sonar_cooldowns.tick();
+        game.stats.turns += 1;
+        let layer = game.player_position.2 as usize;
+        if game.stats.turns_per_layer.len() <= layer{
+            game.stats.turns_per_layer.resize(layer + 1, 0);
+        }
+        game.stats.turns_per_layer[layer] += 1;
+
+        if !is_demo{
+            if game.stats.rocks_destroyed > rocks_destroyed{
+                profile.rocks_destroyed += game.stats.rocks_destroyed - rocks_destroyed;
+                rocks_destroyed = game.stats.rocks_destroyed;
+                if let Err(e) = save_profile(&profile){
+                    println!("Falha ao salvar o perfil: {}", e);
+                }
             }
-            None => {
-                println!("Comando inválido ou ainda não implementado");
+            check_achievements(&game, &mut profile, config.accessible);
+        }
+
+        if let Some(snapshot) = snapshot{
+            record_turn(&mut history, snapshot, &mut game);
+        }
+
+        if game.rules.ironman && !is_demo{
+            autosave(&mut game);
+        }
+
+        if game.hull < hull_before && !queued_actions.is_empty(){
+            println!("O submarino sofreu danos, os comandos restantes foram cancelados!");
+            queued_actions.clear();
+        }
+    }
+
+    print_stats(&game);
+
+    if game.rules.ironman && !is_demo{
+        autosave(&mut game);
+    }
+}
+
+const MAX_UNDO_TURNS: usize = 30;
+
+struct CellChange{
+    player_map: bool,
+    position: (usize, usize, usize),
+    before: String,
+    after: String,
+}
+
+struct TurnRecord{
+    state: Game,
+    changes: Vec<CellChange>,
+}
+
+#[derive(Default)]
+struct UndoHistory{
+    undo: VecDeque<TurnRecord>,
+    redo: Vec<TurnRecord>,
+}
+
+struct TurnSnapshot{
+    game: Game,
+    real_map: Vec<Vec<Vec<String>>>,
+    player_map: Vec<Vec<Vec<String>>>,
+}
+
+fn without_maps(game: &mut Game) -> Game {
+    let real_map = std::mem::take(&mut game.real_map);
+    let player_map = std::mem::take(&mut game.player_map);
+    let copy = game.clone();
+    game.real_map = real_map;
+    game.player_map = player_map;
+    copy
+}
+
+fn take_snapshot(game: &mut Game) -> TurnSnapshot {
+    TurnSnapshot{
+        game: without_maps(game),
+        real_map: game.real_map.clone(),
+        player_map: game.player_map.clone(),
+    }
+}
+
+fn map_changes(before: &[Vec<Vec<String>>], after: &[Vec<Vec<String>>], player_map: bool, changes: &mut Vec<CellChange>){
+    for (z, layer) in after.iter().enumerate(){
+        for (y, row) in layer.iter().enumerate(){
+            for (x, cell) in row.iter().enumerate(){
+                if before[z][y][x] != *cell {
+                    changes.push(CellChange{ player_map, position: (z, y, x), before: before[z][y][x].clone(), after: cell.clone() });
+                }
             }
         }
+    }
+}
+
+fn record_turn(history: &mut UndoHistory, snapshot: TurnSnapshot, game: &mut Game){
+    let mut changes = Vec::new();
+    map_changes(&snapshot.real_map, &game.real_map, false, &mut changes);
+    map_changes(&snapshot.player_map, &game.player_map, true, &mut changes);
+    history.undo.push_back(TurnRecord{ state: snapshot.game, changes });
+    if history.undo.len() > MAX_UNDO_TURNS {
+        history.undo.pop_front();
+    }
+    history.redo.clear();
+}
+
+fn restore_turn(game: &mut Game, state: &Game, changes: &[CellChange], undo: bool){
+    let mut restored = state.clone();
+    restored.real_map = std::mem::take(&mut game.real_map);
+    restored.player_map = std::mem::take(&mut game.player_map);
+    restored.elapsed_ms = game.elapsed_ms;
+    restored.waypoints = std::mem::take(&mut game.waypoints);
+    for change in changes{
+        let map = if change.player_map { &mut restored.player_map } else { &mut restored.real_map };
+        let (z, y, x) = change.position;
+        map[z][y][x] = if undo { change.before.clone() } else { change.after.clone() };
+    }
+    *game = restored;
+}
+
+fn undo_turn(game: &mut Game, history: &mut UndoHistory){
+    if !game.rules.allows_undo() {
+        println!("A dificuldade '{}' não permite desfazer turnos!", game.rules.name);
+        return;
+    }
+    match history.undo.pop_back() {
+        Some(record) => {
+            let after = without_maps(game);
+            restore_turn(game, &record.state, &record.changes, true);
+            println!("Turno desfeito! ({} restantes)", history.undo.len());
+            history.redo.push(TurnRecord{ state: after, changes: record.changes });
+        }
+        None => println!("Não há turnos para desfazer!"),
+    }
+}
+
+fn redo_turn(game: &mut Game, history: &mut UndoHistory){
+    if !game.rules.allows_undo() {
+        println!("A dificuldade '{}' não permite refazer turnos!", game.rules.name);
+        return;
+    }
+    match history.redo.pop() {
+        Some(record) => {
+            let before = without_maps(game);
+            restore_turn(game, &record.state, &record.changes, false);
+            println!("Turno refeito!");
+            history.undo.push_back(TurnRecord{ state: before, changes: record.changes });
+        }
+        None => println!("Não há turnos para refazer!"),
+    }
+}
+
+fn time_expired(game: &mut Game, clock: &mut Instant) -> bool {
+    game.elapsed_ms += clock.elapsed().as_millis() as u64;
+    *clock = Instant::now();
+    if game.time_limit_ms.is_some_and(|limit| game.elapsed_ms >= limit){
+        println!("O tempo acabou!");
+        println!("O submarino é chamado de volta à superfície antes de encontrar o tesouro...");
+        println!("FIM DE JOGO");
+        game.outcome = Some(Outcome::Lost);
+        return true;
+    }
+    false
+}
 
-        game.oxygen -= 1;
+fn autosave(game: &mut Game){
+    game.last_save = Utc::now();
+    if let Err(e) = write_save_file(game){
+        println!("Falha ao salvar o jogo automaticamente: {}", e);
+    }
+}
+
+fn set_accessible_mode(accessible: bool){
+    let mut config = load_user_config().unwrap_or_default();
+    config.accessible = accessible;
+    save_user_config(&config);
+    if accessible {
+        println!("Modo acessível ligado. O mapa será descrito em texto, sem cores nem desenhos.");
+    }else{
+        println!("Modo acessível desligado.");
     }
 }
 
@@ -599,6 +3905,200 @@ fn main() {
         let _ =fs::create_dir("saves");
     }
 
+    for arg in std::env::args().skip(1){
+        match arg.as_str() {
+            "--accessible" | "--acessivel" => set_accessible_mode(true),
+            "--no-accessible" | "--sem-acessivel" => set_accessible_mode(false),
+            _ => println!("Opção desconhecida: {}", arg),
+        }
+    }
+
     title_screen();
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binding(key: &str, command: &str) -> KeyBinding {
+        KeyBinding{ key: key.to_string(), command: command.to_string() }
+    }
+
+    fn parse(input: &str, config: &Config) -> Result<Vec<Action>, ParseError> {
+        let keymap = vec![binding("n", "move north"), binding("e", "move east"), binding("mv", "move")];
+        parse_player_input(input, &CommandContext{ keymap: &keymap, config })
+    }
+
+    fn expect_error<T>(result: Result<T, ParseError>) -> ParseError {
+        match result {
+            Ok(_) => panic!("a entrada deveria ter sido recusada"),
+            Err(error) => error,
+        }
+    }
+
+    #[test]
+    fn tokenize_splits_commands_and_keeps_columns(){
+        let commands = tokenize("Move north 5;  scan ;");
+        assert_eq!(commands.len(), 2);
+        let texts: Vec<&str> = commands[0].iter().map(|token| token.text.as_str()).collect();
+        assert_eq!(texts, ["move", "north", "5"]);
+        assert_eq!((commands[0][1].column, commands[0][1].width), (5, 5));
+        assert_eq!((commands[1][0].text.as_str(), commands[1][0].column), ("scan", 15));
+    }
+
+    #[test]
+    fn resolve_token_accepts_unique_prefixes(){
+        let token = &tokenize("nor")[0][0];
+        assert!(matches!(resolve_token(token, &DIRECTIONS, "direção"), Ok(Direction::North)));
+    }
+
+    #[test]
+    fn resolve_token_rejects_ambiguous_prefixes(){
+        let token = &tokenize("m")[0][0];
+        let error = expect_error(resolve_token(token, &VERBS.map(|verb| (verb, verb)), "comando"));
+        assert_eq!((error.column, error.width), (0, 1));
+        assert!(error.message.contains("ambíguo"));
+        assert!(error.message.contains("move") && error.message.contains("map") && error.message.contains("mark"));
+    }
+
+    #[test]
+    fn parse_command_reads_the_repeat_count(){
+        let (action, repeats) = parse_command(&tokenize("move north 5")[0]).unwrap();
+        assert!(matches!(action, Action::Move(Direction::North)));
+        assert_eq!(repeats, 5);
+    }
+
+    #[test]
+    fn keymap_shortcuts_expand_before_parsing(){
+        let actions = parse("mv e", &Config::default()).unwrap();
+        assert_eq!(actions.len(), 1);
+        assert!(matches!(actions[0], Action::Move(Direction::East)));
+
+        let actions = parse("n 3; e", &Config::default()).unwrap();
+        assert_eq!(actions.len(), 4);
+        assert!(actions[..3].iter().all(|action| matches!(action, Action::Move(Direction::North))));
+        assert!(matches!(actions[3], Action::Move(Direction::East)));
+    }
+
+    #[test]
+    fn zero_repeats_point_at_the_count(){
+        let error = expect_error(parse("n 0", &Config::default()));
+        assert_eq!((error.column, error.width), (2, 1));
+        assert!(error.message.contains("repetições"));
+    }
+
+    #[test]
+    fn ambiguous_commands_are_rejected(){
+        let error = expect_error(parse("m", &Config::default()));
+        assert_eq!((error.column, error.width), (0, 1));
+        assert!(error.message.contains("ambíguo"));
+    }
+
+    #[test]
+    fn aliases_and_macros_expand(){
+        let config = Config{
+            aliases: vec![binding("fw", "move north")],
+            macros: vec![Macro{ name: "patrulha".to_string(), commands: "scan; fw; fw".to_string() }],
+            ..Config::default()
+        };
+        let actions = parse("fw 2", &config).unwrap();
+        assert_eq!(actions.len(), 2);
+        assert!(actions.iter().all(|action| matches!(action, Action::Move(Direction::North))));
+
+        let actions = parse("macro run patrulha", &config).unwrap();
+        assert_eq!(actions.len(), 3);
+        assert!(matches!(actions[0], Action::Scan(SonarMode::Short)));
+    }
+
+    #[test]
+    fn recursive_aliases_are_rejected(){
+        let config = Config{
+            aliases: vec![binding("ida", "volta"), binding("volta", "scan; ida")],
+            ..Config::default()
+        };
+        let error = expect_error(expand_user_command(&tokenize("ida")[0], &CommandContext{ keymap: &[], config: &config }, &mut Vec::new()));
+        assert_eq!((error.column, error.width), (0, 3));
+        assert!(error.message.contains("recursivamente"));
+        assert!(error.message.contains("ida -> volta -> ida"));
+    }
+
+    fn test_game() -> Game {
+        let _ = fs::create_dir("saves");
+        new_game("Teste".to_string(), Rules::default(), "assets/base_map.csv").expect("Falha ao carregar mapa")
+    }
+
+    fn play_turn(game: &mut Game, history: &mut UndoHistory){
+        let snapshot = take_snapshot(game);
+        game.oxygen -= 10;
+        game.hull = 80;
+        game.credits = 50;
+        game.stats.turns += 1;
+        game.real_map[1][10][10] = "pearl".to_string();
+        game.player_map[1][10][10] = "pearl".to_string();
+        record_turn(history, snapshot, game);
+    }
+
+    #[test]
+    fn undo_and_redo_restore_the_turn(){
+        let mut game = test_game();
+        let mut history = UndoHistory::default();
+        let real_before = game.real_map[1][10][10].clone();
+        let player_before = game.player_map[1][10][10].clone();
+        play_turn(&mut game, &mut history);
+
+        undo_turn(&mut game, &mut history);
+        assert_eq!((game.oxygen, game.hull, game.credits, game.stats.turns), (240, 100, 0, 0));
+        assert_eq!(game.real_map[1][10][10], real_before);
+        assert_eq!(game.player_map[1][10][10], player_before);
+        assert_eq!((history.undo.len(), history.redo.len()), (0, 1));
+
+        redo_turn(&mut game, &mut history);
+        assert_eq!((game.oxygen, game.hull, game.credits, game.stats.turns), (230, 80, 50, 1));
+        assert_eq!(game.real_map[1][10][10], "pearl");
+        assert_eq!(game.player_map[1][10][10], "pearl");
+        assert_eq!((history.undo.len(), history.redo.len()), (1, 0));
+    }
+
+    #[test]
+    fn undo_and_redo_keep_waypoints_and_elapsed_time(){
+        let mut game = test_game();
+        let mut history = UndoHistory::default();
+        play_turn(&mut game, &mut history);
+        add_waypoint(&mut game, None, "base".to_string());
+        game.elapsed_ms = 5000;
+
+        undo_turn(&mut game, &mut history);
+        assert_eq!(game.oxygen, 240);
+        assert_eq!(game.waypoints.len(), 1);
+        assert_eq!(game.elapsed_ms, 5000);
+
+        game.elapsed_ms = 7000;
+        redo_turn(&mut game, &mut history);
+        assert_eq!(game.oxygen, 230);
+        assert_eq!(game.waypoints.len(), 1);
+        assert_eq!(game.elapsed_ms, 7000);
+    }
+
+    #[test]
+    fn new_turns_clear_the_redo_history(){
+        let mut game = test_game();
+        let mut history = UndoHistory::default();
+        play_turn(&mut game, &mut history);
+        undo_turn(&mut game, &mut history);
+        play_turn(&mut game, &mut history);
+        assert_eq!((history.undo.len(), history.redo.len()), (1, 0));
+    }
+
+    #[test]
+    fn ironman_games_refuse_undo(){
+        let mut game = test_game();
+        game.rules.ironman = true;
+        let mut history = UndoHistory::default();
+        play_turn(&mut game, &mut history);
+
+        undo_turn(&mut game, &mut history);
+        assert_eq!(game.oxygen, 230);
+        assert_eq!(history.undo.len(), 1);
+    }
+}